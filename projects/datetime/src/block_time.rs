@@ -0,0 +1,213 @@
+//! Estimates between block numbers and wall-clock time.
+//!
+//! Governance and vesting are scheduled by block number, but people plan by calendar date. A
+//! [`BlockTimeEstimator`] starts from a block whose timestamp is known and assumes blocks are
+//! produced at an average block time from there on. Chains that changed their block time are
+//! described by several [`Era`]s, each starting at a block with its own block time.
+//!
+//! ```
+//! use datetime::{BlockTimeEstimator, Timestamp};
+//!
+//! // Block 1,000 was produced at 2023-03-05T12:00:00Z, with 6 second blocks up to block 2,000
+//! // and 12 second blocks from then on.
+//! let estimator = BlockTimeEstimator::new(1_000, "2023-03-05T12:00:00Z".parse().unwrap(), 6_000)
+//!     .unwrap()
+//!     .then(2_000, 12_000)
+//!     .unwrap();
+//!
+//! assert_eq!(estimator.timestamp_at(2_010).unwrap().to_rfc3339(), "2023-03-05T13:42:00Z");
+//! assert_eq!(estimator.block_at("2023-03-05T13:42:11Z".parse().unwrap()).unwrap(), 2_010);
+//! ```
+
+use chrono::Duration;
+
+use crate::error::{DateError, DispatchError};
+use crate::Timestamp;
+
+pub type BlockNumber = u32;
+
+/// A run of blocks produced at the same average block time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Era {
+    /// First block of the era.
+    pub start_block: BlockNumber,
+    /// Timestamp of `start_block`, observed or estimated.
+    pub start: Timestamp,
+    /// Average time between two blocks, in milliseconds.
+    pub block_time_millis: u64,
+}
+
+/// Converts between block numbers and timestamps across one or more [`Era`]s.
+///
+/// Blocks before the first era are extrapolated backwards with its block time, blocks after the
+/// start of the last era forwards with the last block time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTimeEstimator {
+    eras: Vec<Era>,
+}
+
+impl BlockTimeEstimator {
+    /// Starts from the reference point `(block, timestamp)` with a single block time.
+    pub fn new(block: BlockNumber, timestamp: Timestamp, block_time_millis: u64) -> Result<Self, DispatchError> {
+        Self::from_eras(vec![Era { start_block: block, start: timestamp, block_time_millis }])
+    }
+
+    /// Takes eras ordered by start block, e.g. with start timestamps observed on chain.
+    ///
+    /// Every era needs a non-zero block time, and must start at a later block and a later time
+    /// than the one before.
+    pub fn from_eras(eras: Vec<Era>) -> Result<Self, DispatchError> {
+        let invalid = |index, reason| DateError::InvalidEra { index, reason }.into();
+        if eras.is_empty() {
+            return Err(invalid(0, "at least one era is needed"));
+        }
+        for (index, era) in eras.iter().enumerate() {
+            if era.block_time_millis == 0 {
+                return Err(invalid(index, "block time must not be zero"));
+            }
+            if index > 0 && era.start_block <= eras[index - 1].start_block {
+                return Err(invalid(index, "eras must start at increasing block numbers"));
+            }
+            if index > 0 && era.start <= eras[index - 1].start {
+                return Err(invalid(index, "eras must start at increasing timestamps"));
+            }
+        }
+        Ok(BlockTimeEstimator { eras })
+    }
+
+    /// Adds an era from `start_block` on, whose start is estimated with the current last era.
+    pub fn then(mut self, start_block: BlockNumber, block_time_millis: u64) -> Result<Self, DispatchError> {
+        let start = self.timestamp_at(start_block)?;
+        self.eras.push(Era { start_block, start, block_time_millis });
+        Self::from_eras(self.eras)
+    }
+
+    pub fn eras(&self) -> &[Era] {
+        &self.eras
+    }
+
+    /// Estimated timestamp of `block`.
+    pub fn timestamp_at(&self, block: BlockNumber) -> Result<Timestamp, DispatchError> {
+        let era = self.eras.iter().rev().find(|era| era.start_block <= block).unwrap_or(&self.eras[0]);
+        let blocks = i128::from(block) - i128::from(era.start_block);
+        let millis = blocks * i128::from(era.block_time_millis);
+        let millis = i64::try_from(millis).map_err(|_| DateError::BlockOutOfRange { block })?;
+        era.start
+            .checked_add(Duration::milliseconds(millis))
+            .map_err(|_| DateError::BlockOutOfRange { block }.into())
+    }
+
+    /// Estimated number of the last block produced at or before `timestamp`.
+    pub fn block_at(&self, timestamp: Timestamp) -> Result<BlockNumber, DispatchError> {
+        let index = self.eras.iter().rposition(|era| era.start <= timestamp).unwrap_or(0);
+        let era = &self.eras[index];
+        let millis = duration_millis(timestamp.duration_since(era.start));
+        let block = i128::from(era.start_block) + millis.div_euclid(i128::from(era.block_time_millis));
+        // Observed era starts may not line up with the previous block time, so never estimate
+        // past the block the next era starts at.
+        let block = match self.eras.get(index + 1) {
+            Some(next) => block.min(i128::from(next.start_block) - 1),
+            None => block,
+        };
+        BlockNumber::try_from(block).map_err(|_| DateError::NoBlockAt { timestamp }.into())
+    }
+}
+
+fn duration_millis(duration: Duration) -> i128 {
+    let secs = i128::from(duration.num_seconds());
+    let subsec = duration - Duration::seconds(duration.num_seconds());
+    secs * 1_000 + i128::from(subsec.num_milliseconds())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> Timestamp {
+        rfc3339.parse().unwrap()
+    }
+
+    fn six_then_twelve_seconds() -> BlockTimeEstimator {
+        BlockTimeEstimator::new(1_000, at("2023-03-05T12:00:00Z"), 6_000).unwrap().then(2_000, 12_000).unwrap()
+    }
+
+    #[test]
+    fn estimates_future_and_past_blocks() {
+        let estimator = BlockTimeEstimator::new(1_000, at("2023-03-05T12:00:00Z"), 6_000).unwrap();
+
+        assert_eq!(estimator.timestamp_at(1_010).unwrap(), at("2023-03-05T12:01:00Z"));
+        assert_eq!(estimator.timestamp_at(990).unwrap(), at("2023-03-05T11:59:00Z"));
+        assert_eq!(estimator.timestamp_at(0).unwrap(), at("2023-03-05T10:20:00Z"));
+    }
+
+    #[test]
+    fn estimates_the_block_at_a_date() {
+        let estimator = BlockTimeEstimator::new(1_000, at("2023-03-05T12:00:00Z"), 6_000).unwrap();
+
+        assert_eq!(estimator.block_at(at("2023-03-05T12:01:00Z")).unwrap(), 1_010);
+        assert_eq!(estimator.block_at(at("2023-03-05T12:01:05.999Z")).unwrap(), 1_010);
+        assert_eq!(estimator.block_at(at("2023-03-05T11:59:59Z")).unwrap(), 999);
+        assert!(matches!(
+            estimator.block_at(at("2023-03-05T10:19:59Z")).unwrap_err().kind(),
+            DateError::NoBlockAt { .. }
+        ));
+    }
+
+    #[test]
+    fn switches_block_time_between_eras() {
+        let estimator = six_then_twelve_seconds();
+
+        assert_eq!(estimator.eras()[1].start, at("2023-03-05T13:40:00Z"));
+        assert_eq!(estimator.timestamp_at(1_999).unwrap(), at("2023-03-05T13:39:54Z"));
+        assert_eq!(estimator.timestamp_at(2_001).unwrap(), at("2023-03-05T13:40:12Z"));
+        assert_eq!(estimator.block_at(at("2023-03-05T13:39:59Z")).unwrap(), 1_999);
+        assert_eq!(estimator.block_at(at("2023-03-05T13:40:12Z")).unwrap(), 2_001);
+    }
+
+    #[test]
+    fn round_trips_blocks_across_eras() {
+        let estimator = six_then_twelve_seconds().then(5_000, 3_000).unwrap();
+
+        for block in (0..10_000).step_by(97) {
+            assert_eq!(estimator.block_at(estimator.timestamp_at(block).unwrap()).unwrap(), block);
+        }
+    }
+
+    #[test]
+    fn observed_era_starts_bound_the_previous_era() {
+        // The second era started later than 6 second blocks would predict.
+        let estimator = BlockTimeEstimator::from_eras(vec![
+            Era { start_block: 0, start: at("2023-01-01T00:00:00Z"), block_time_millis: 6_000 },
+            Era { start_block: 100, start: at("2023-01-01T00:20:00Z"), block_time_millis: 6_000 },
+        ])
+        .unwrap();
+
+        assert_eq!(estimator.block_at(at("2023-01-01T00:19:00Z")).unwrap(), 99);
+        assert_eq!(estimator.block_at(at("2023-01-01T00:20:06Z")).unwrap(), 101);
+    }
+
+    #[test]
+    fn rejects_invalid_eras() {
+        let era = |start_block, start: &str, block_time_millis| Era { start_block, start: at(start), block_time_millis };
+        let invalid = [
+            vec![],
+            vec![era(0, "2023-01-01T00:00:00Z", 0)],
+            vec![era(10, "2023-01-01T00:00:00Z", 6_000), era(10, "2023-01-02T00:00:00Z", 6_000)],
+            vec![era(10, "2023-01-01T00:00:00Z", 6_000), era(20, "2023-01-01T00:00:00Z", 6_000)],
+        ];
+
+        for eras in invalid {
+            assert!(matches!(
+                BlockTimeEstimator::from_eras(eras.clone()).unwrap_err().kind(),
+                DateError::InvalidEra { .. }
+            ), "{:?}", eras);
+        }
+    }
+
+    #[test]
+    fn reports_blocks_beyond_the_date_range() {
+        let estimator = BlockTimeEstimator::new(0, Timestamp::unix_epoch(), u64::MAX).unwrap();
+
+        assert!(matches!(estimator.timestamp_at(BlockNumber::MAX).unwrap_err().kind(), DateError::BlockOutOfRange { .. }));
+    }
+}
@@ -0,0 +1,124 @@
+//! Sources of the current time.
+//!
+//! Code that needs "now" takes a [`Clock`] rather than reading the system time, so tests can pin
+//! it with a [`FixedClock`] or step it with a [`ManualClock`].
+
+use std::sync::Mutex;
+
+use chrono::Duration;
+
+use crate::error::DispatchError;
+use crate::Timestamp;
+
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+}
+
+/// The system's wall-clock time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from(chrono::Utc::now())
+    }
+}
+
+/// Always returns the same timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub Timestamp);
+
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        self.0
+    }
+}
+
+/// Stands still until it is set or advanced, and can be shared between threads.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<Timestamp>,
+}
+
+impl ManualClock {
+    pub fn new(now: Timestamp) -> Self {
+        ManualClock { now: Mutex::new(now) }
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Moves the clock by `duration`, which may be negative, and returns the new time.
+    pub fn advance(&self, duration: Duration) -> Result<Timestamp, DispatchError> {
+        let mut now = self.now.lock().unwrap();
+        *now = now.checked_add(duration)?;
+        Ok(*now)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DateError;
+
+    fn at(rfc3339: &str) -> Timestamp {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn fixed_clock_stands_still() {
+        let clock = FixedClock(at("2023-03-05T12:00:00.250Z"));
+
+        assert_eq!(clock.now().to_rfc3339(), "2023-03-05T12:00:00.250Z");
+        assert_eq!(clock.now().as_millis(), 1_678_017_600_250);
+    }
+
+    #[test]
+    fn manual_clock_moves_when_told() {
+        let clock = ManualClock::new(at("2023-03-05T12:00:00Z"));
+
+        assert_eq!(clock.advance(Duration::milliseconds(1_500)).unwrap(), at("2023-03-05T12:00:01.500Z"));
+        assert_eq!(clock.advance(Duration::hours(-1)).unwrap(), at("2023-03-05T11:00:01.500Z"));
+        assert_eq!(clock.now(), at("2023-03-05T11:00:01.500Z"));
+
+        clock.set(Timestamp::unix_epoch());
+        assert_eq!(clock.now().as_millis(), 0);
+    }
+
+    #[test]
+    fn manual_clock_keeps_its_time_on_overflow() {
+        let clock = ManualClock::new(at("2023-03-05T12:00:00Z"));
+
+        assert!(matches!(
+            clock.advance(Duration::days(365 * 1_000_000)).unwrap_err().kind(),
+            DateError::ArithmeticOverflow { .. }
+        ));
+        assert_eq!(clock.now(), at("2023-03-05T12:00:00Z"));
+    }
+
+    #[test]
+    fn system_clock_is_after_the_epoch() {
+        let clock: Box<dyn Clock> = Box::new(SystemClock);
+
+        assert!(clock.now() > at("2023-01-01T00:00:00Z"));
+    }
+}
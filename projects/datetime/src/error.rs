@@ -0,0 +1,155 @@
+//! Errors of the datetime tool.
+//!
+//! [`DispatchError`] is what the binary reports. It wraps a [`DateError`] describing what went
+//! wrong, which in turn may carry lower level causes such as [`ConvertI64ToU64ForDate`]. Use
+//! [`render`] to print the whole chain and [`DispatchError::exit_code`] to map it to a process
+//! exit status.
+
+use std::error::Error as StdError;
+use std::fmt::Write;
+
+use chrono::Duration;
+use thiserror::Error;
+
+use crate::{BlockNumber, Timestamp};
+
+// https://dev.to/nathan20/how-to-handle-errors-in-rust-a-comprehensive-guide-1cco
+#[derive(Debug, Error)]
+#[error("dispatch error")]
+pub struct DispatchError {
+    #[from]
+    source: DateError,
+}
+
+#[derive(Debug, Error)]
+#[error("Unable to convert i64 to u64 for date error")]
+pub struct ConvertI64ToU64ForDate;
+
+#[derive(Debug, Error)]
+pub enum DateError {
+    #[error("timestamp {timestamp} is negative")]
+    NegativeTimestamp {
+        timestamp: i64,
+        #[source]
+        source: ConvertI64ToU64ForDate,
+    },
+    #[error("timestamp {timestamp} {unit} is out of the supported date range")]
+    OutOfRange { timestamp: i64, unit: &'static str },
+    #[error("moment {moment} ms does not fit a signed timestamp")]
+    MomentOutOfRange { moment: u64 },
+    #[error("{timestamp} does not fit an i64 of {unit}")]
+    UnitOverflow { timestamp: Timestamp, unit: &'static str },
+    #[error("{timestamp} plus {duration} is out of the supported date range")]
+    ArithmeticOverflow { timestamp: Timestamp, duration: Duration },
+    #[error("cannot parse {input:?} as {format}")]
+    Parse {
+        input: String,
+        format: &'static str,
+        #[source]
+        source: chrono::ParseError,
+    },
+    #[error("era {index}: {reason}")]
+    InvalidEra { index: usize, reason: &'static str },
+    #[error("block {block} is out of the supported date range")]
+    BlockOutOfRange { block: BlockNumber },
+    #[error("no block number is estimated at {timestamp}")]
+    NoBlockAt { timestamp: Timestamp },
+    #[error("cannot parse {input:?} as an ISO week date like 2023-W09-7")]
+    InvalidWeekDate { input: String },
+    #[error("invalid timezone {timezone:?}: {reason}")]
+    Timezone { timezone: String, reason: &'static str },
+    #[error("invalid schedule {expression:?}: {reason}")]
+    InvalidSchedule { expression: String, reason: String },
+    #[error("invalid strftime format {format:?}")]
+    InvalidFormat { format: String },
+}
+
+/// Exit statuses from BSD `sysexits.h`.
+pub mod exit_code {
+    /// The command was used incorrectly, e.g. with an unparseable argument.
+    pub const USAGE: u8 = 64;
+    /// The input data was incorrect.
+    pub const DATA: u8 = 65;
+    /// Something was found in an unconfigured or misconfigured state.
+    pub const CONFIG: u8 = 78;
+}
+
+impl DispatchError {
+    pub fn kind(&self) -> &DateError {
+        &self.source
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self.source {
+            DateError::NegativeTimestamp { .. }
+            | DateError::OutOfRange { .. }
+            | DateError::MomentOutOfRange { .. }
+            | DateError::UnitOverflow { .. }
+            | DateError::ArithmeticOverflow { .. }
+            | DateError::BlockOutOfRange { .. }
+            | DateError::NoBlockAt { .. } => exit_code::DATA,
+            DateError::Parse { .. }
+            | DateError::InvalidWeekDate { .. }
+            | DateError::InvalidSchedule { .. }
+            | DateError::InvalidFormat { .. } => exit_code::USAGE,
+            DateError::Timezone { .. } | DateError::InvalidEra { .. } => exit_code::CONFIG,
+        }
+    }
+}
+
+/// Iterates over `error` and everything it was caused by, outermost first.
+pub fn chain<'a>(error: &'a (dyn StdError + 'static)) -> impl Iterator<Item = &'a (dyn StdError + 'static)> {
+    std::iter::successors(Some(error), |&error| error.source())
+}
+
+/// Renders `error` on one line followed by an indented `caused by:` line per source.
+pub fn render(error: &(dyn StdError + 'static)) -> String {
+    let mut rendered = String::new();
+    for (depth, error) in chain(error).enumerate() {
+        if depth == 0 {
+            write!(rendered, "error: {}", error).unwrap();
+        } else {
+            write!(rendered, "\n  caused by: {}", error).unwrap();
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negative() -> DispatchError {
+        DateError::NegativeTimestamp { timestamp: -1, source: ConvertI64ToU64ForDate }.into()
+    }
+
+    #[test]
+    fn renders_the_whole_source_chain() {
+        assert_eq!(
+            render(&negative()),
+            "error: dispatch error\n  \
+             caused by: timestamp -1 is negative\n  \
+             caused by: Unable to convert i64 to u64 for date error"
+        );
+    }
+
+    #[test]
+    fn renders_errors_without_source_on_one_line() {
+        assert_eq!(render(&ConvertI64ToU64ForDate), "error: Unable to convert i64 to u64 for date error");
+    }
+
+    #[test]
+    fn maps_errors_to_exit_codes() {
+        let parse = chrono::DateTime::parse_from_rfc3339("yesterday").unwrap_err();
+        let cases = [
+            (negative(), exit_code::DATA),
+            (DateError::OutOfRange { timestamp: i64::MAX, unit: "ms" }.into(), exit_code::DATA),
+            (DateError::Parse { input: "yesterday".into(), format: "RFC 3339", source: parse }.into(), exit_code::USAGE),
+            (DateError::Timezone { timezone: "Mars/Olympus".into(), reason: "unknown" }.into(), exit_code::CONFIG),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{}", render(&error));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Conversions between chain timestamps and dates.
+//!
+//! Timestamps are Unix milliseconds stored as `i64`, the way `chrono` returns them, while the
+//! runtime expects them as `u64`. [`Timestamp`] converts between these and the other units and
+//! formats, and [`BlockTimeEstimator`] between block numbers and timestamps. [`TimezoneFormatter`]
+//! renders timestamps in IANA timezones and [`Schedule`] finds the next runs of a cron expression
+//! in one. The current time comes from a [`Clock`], which tests replace with a fixed one. Every
+//! fallible conversion returns a [`DispatchError`].
+
+use chrono::FixedOffset;
+
+mod block_time;
+mod clock;
+pub mod error;
+mod logging;
+mod schedule;
+mod timestamp;
+mod timezone;
+
+pub use block_time::{BlockNumber, BlockTimeEstimator, Era};
+pub use clock::{Clock, FixedClock, ManualClock, SystemClock};
+pub use error::{render, ConvertI64ToU64ForDate, DateError, DispatchError};
+pub use logging::{format_record, LogConfig, LogFormat};
+pub use schedule::{Occurrences, Schedule};
+pub use timestamp::Timestamp;
+pub use timezone::{parse_timezone, TimezoneFormatter};
+
+pub type Date = i64;
+
+/// Converts Unix milliseconds into a `pallet_timestamp` moment.
+pub fn convert_i64_to_u64_in_milliseconds(date: Date) -> Result<u64, DispatchError> {
+    Timestamp::from_millis(date)?.as_moment()
+}
+
+/// Parses a UTC offset given as `Z`, `+HH`, `+HH:MM` or `+HHMM`.
+pub fn parse_utc_offset(timezone: &str) -> Result<FixedOffset, DispatchError> {
+    let invalid = |reason| DateError::Timezone { timezone: timezone.to_string(), reason }.into();
+    if timezone == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+    let (sign, digits) = match timezone.split_at(timezone.len().min(1)) {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return Err(invalid("expected Z or an offset starting with + or -")),
+    };
+    let digits = digits.replacen(':', "", 1);
+    if !matches!(digits.len(), 2 | 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid("expected HH, HH:MM or HHMM after the sign"));
+    }
+    let hours: i32 = digits[..2].parse().unwrap();
+    let minutes: i32 = digits.get(2..).filter(|m| !m.is_empty()).map_or(0, |m| m.parse().unwrap());
+    if minutes >= 60 {
+        return Err(invalid("minutes must be below 60"));
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(|| invalid("offset must be below 24 hours"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_non_negative_millis() {
+        assert_eq!(convert_i64_to_u64_in_milliseconds(1_678_000_000_000).unwrap(), 1_678_000_000_000);
+        assert!(matches!(
+            convert_i64_to_u64_in_milliseconds(-1).unwrap_err().kind(),
+            DateError::NegativeTimestamp { timestamp: -1, .. }
+        ));
+    }
+
+    #[test]
+    fn parses_utc_offsets() {
+        assert_eq!(parse_utc_offset("Z").unwrap().local_minus_utc(), 0);
+        assert_eq!(parse_utc_offset("+05:30").unwrap().local_minus_utc(), 19_800);
+        assert_eq!(parse_utc_offset("-0800").unwrap().local_minus_utc(), -28_800);
+        for invalid in ["", "05:00", "+5", "+05:60", "+24", "+0a"] {
+            assert!(matches!(parse_utc_offset(invalid).unwrap_err().kind(), DateError::Timezone { .. }), "{}", invalid);
+        }
+    }
+}
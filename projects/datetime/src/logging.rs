@@ -0,0 +1,222 @@
+//! Log output as human readable or JSON lines.
+//!
+//! The level comes from `--log-level`, else from the directives in `RUST_LOG`, else defaults to
+//! `info`. `RUST_LOG` is only ever read. Key/values attached to a record, as in
+//! `info!(utc_millis = 0; "epoch")`, become fields of the JSON object or trailing `key=value`
+//! pairs of the human line.
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use clap::Args;
+use env_logger::fmt::Formatter;
+use log::kv::{self, Key, Value, VisitSource};
+use log::{LevelFilter, Record};
+use serde_json::{Map, Value as Json};
+
+use crate::{Clock, Timestamp};
+
+/// How each log record is written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `[INFO  datetime] message key=value`
+    #[default]
+    Human,
+    /// One JSON object per line with `timestamp`, `level`, `target`, `message` and the key/values.
+    Json,
+}
+
+impl LogFormat {
+    pub const NAMES: [&'static str; 2] = ["human", "json"];
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogFormat::Human => Self::NAMES[0],
+            LogFormat::Json => Self::NAMES[1],
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+/// Logging options, usually flattened into a command line parser as `--log-format <human|json>`
+/// and `--log-level <level>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Args)]
+pub struct LogConfig {
+    /// human or json.
+    #[arg(long = "log-format", default_value_t = LogFormat::Human)]
+    pub format: LogFormat,
+    /// off, error, warn, info, debug or trace. Overrides `RUST_LOG` when set.
+    #[arg(long = "log-level")]
+    pub level: Option<LevelFilter>,
+}
+
+impl LogConfig {
+    /// A logger writing to stderr, filtered by `rust_log` directives unless a level was given, and
+    /// stamping JSON lines with the time of `clock`.
+    pub fn builder(&self, rust_log: Option<&str>, clock: impl Clock + Send + Sync + 'static) -> env_logger::Builder {
+        let mut builder = env_logger::Builder::new();
+        match (self.level, rust_log) {
+            (Some(level), _) => builder.filter_level(level),
+            (None, Some(directives)) => builder.parse_filters(directives),
+            (None, None) => builder.filter_level(LevelFilter::Info),
+        };
+        let format = self.format;
+        builder.format(move |buf: &mut Formatter, record: &Record| {
+            writeln!(buf, "{}", format_record(format, record, clock.now()))
+        });
+        builder
+    }
+
+    /// Installs the logger, reading but never setting `RUST_LOG`.
+    pub fn init(&self, clock: impl Clock + Send + Sync + 'static) -> Result<(), log::SetLoggerError> {
+        let rust_log = std::env::var("RUST_LOG").ok();
+        self.builder(rust_log.as_deref(), clock).try_init()
+    }
+}
+
+/// Renders one record without the trailing newline.
+pub fn format_record(format: LogFormat, record: &Record, now: Timestamp) -> String {
+    match format {
+        LogFormat::Human => {
+            let mut line = format!("[{:<5} {}] {}", record.level(), record.target(), record.args());
+            let mut fields = HumanFields(&mut line);
+            let _ = record.key_values().visit(&mut fields);
+            line
+        }
+        LogFormat::Json => {
+            let mut object = Map::new();
+            object.insert("timestamp".into(), now.to_rfc3339().into());
+            object.insert("level".into(), record.level().as_str().into());
+            object.insert("target".into(), record.target().into());
+            object.insert("message".into(), record.args().to_string().into());
+            let _ = record.key_values().visit(&mut JsonFields(&mut object));
+            Json::Object(object).to_string()
+        }
+    }
+}
+
+struct HumanFields<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for HumanFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        use std::fmt::Write;
+        write!(self.0, " {}={}", key, value).map_err(|_| kv::Error::msg("formatting failed"))
+    }
+}
+
+struct JsonFields<'a>(&'a mut Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        // Keep numbers and booleans typed so the pipeline can filter and aggregate on them.
+        let value = if let Some(value) = value.to_u64() {
+            Json::from(value)
+        } else if let Some(value) = value.to_i64() {
+            Json::from(value)
+        } else if let Some(value) = value.to_bool() {
+            Json::from(value)
+        } else if let Some(value) = value.to_f64() {
+            Json::from(value)
+        } else {
+            Json::from(value.to_string())
+        };
+        // Fields may not replace the ones every line has.
+        if !self.0.contains_key(key.as_str()) {
+            self.0.insert(key.to_string(), value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[derive(Debug, clap::Parser)]
+    struct Cli {
+        #[command(flatten)]
+        log: LogConfig,
+        rest: Vec<String>,
+    }
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        <Cli as clap::Parser>::try_parse_from(std::iter::once(&"datetime").chain(args))
+    }
+
+    fn render(format: LogFormat, fields: &[(&str, i64)]) -> String {
+        let now = "2023-03-05T12:00:00.250Z".parse().unwrap();
+        format_record(
+            format,
+            &Record::builder()
+                .level(Level::Info)
+                .target("datetime")
+                .args(format_args!("converted \"{}\"", "now"))
+                .key_values(&fields)
+                .build(),
+            now,
+        )
+    }
+
+    #[test]
+    fn takes_log_options_out_of_the_arguments() {
+        let cli = parse(&["--log-format", "json", "2023-03-05T12:00:00Z", "--log-level=debug", "UTC"]).unwrap();
+
+        assert_eq!(cli.log, LogConfig { format: LogFormat::Json, level: Some(LevelFilter::Debug) });
+        assert_eq!(cli.rest, ["2023-03-05T12:00:00Z", "UTC"]);
+        assert_eq!(parse(&["now"]).unwrap().log, LogConfig::default());
+    }
+
+    #[test]
+    fn rejects_invalid_log_options() {
+        for invalid in [&["--log-format", "xml"][..], &["--log-level=loud"], &["--log-level"]] {
+            assert!(parse(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn renders_human_lines_with_fields() {
+        assert_eq!(
+            render(LogFormat::Human, &[("utc_millis", 1_678_017_600_250)]),
+            "[INFO  datetime] converted \"now\" utc_millis=1678017600250"
+        );
+    }
+
+    #[test]
+    fn renders_json_lines_with_typed_fields() {
+        let line: Json = serde_json::from_str(&render(LogFormat::Json, &[("utc_millis", 1_678_017_600_250)])).unwrap();
+
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "timestamp": "2023-03-05T12:00:00.250Z",
+                "level": "INFO",
+                "target": "datetime",
+                "message": "converted \"now\"",
+                "utc_millis": 1_678_017_600_250u64,
+            })
+        );
+    }
+
+    #[test]
+    fn fields_do_not_replace_the_standard_keys() {
+        let line: Json = serde_json::from_str(&render(LogFormat::Json, &[("level", 1)])).unwrap();
+
+        assert_eq!(line["level"], "INFO");
+    }
+}
@@ -1,55 +1,172 @@
-use log::{trace, error, warn, info, debug, log_enabled};
-use thiserror::Error;
-use chrono::{
-    DateTime,
-    Utc,
+use std::process::ExitCode;
+
+use clap::Parser;
+use datetime::error::{chain, exit_code};
+use datetime::{
+    format_record, parse_utc_offset, render, Clock, DispatchError, LogConfig, LogFormat, Schedule, SystemClock,
+    Timestamp, TimezoneFormatter,
 };
+use log::{debug, info, Level, Record};
 
-// https://dev.to/nathan20/how-to-handle-errors-in-rust-a-comprehensive-guide-1cco
-#[derive(Debug, Error)]
-#[error("dispatch error")]
-struct DispatchError {
-    #[from]
-    source: ConvertI64ToU64ForDate,
-}
+/// How many runs of a schedule to list.
+const NEXT_RUNS: usize = 5;
 
-#[derive(Debug, Error)]
-#[error("Unable to convert i64 to u64 for date error")]
-struct ConvertI64ToU64ForDate;
+/// The converted timestamp and the lines describing it.
+#[derive(Debug)]
+struct Report {
+    timestamp: Timestamp,
+    lines: Vec<String>,
+}
 
-type Date = i64;
+/// Converts a date to the units and formats of a chain timestamp and shows it in other
+/// timezones, optionally with the next runs of a cron expression.
+#[derive(Debug, Parser)]
+struct Cli {
+    #[command(flatten)]
+    log: LogConfig,
+    /// RFC 3339 date, or `now` for the current time, which is also the default.
+    date: Option<String>,
+    /// UTC offset like +05:30, or comma separated IANA timezones.
+    #[arg(allow_hyphen_values = true)]
+    timezones: Option<String>,
+    /// Cron expression whose next runs are listed in the first of the timezones.
+    schedule: Option<String>,
+}
 
-fn convert_i64_to_u64_in_milliseconds(date: Date) -> Result<u64, DispatchError> {
-    let date_as_u64_millis: u64;
-    if let Some(_date_as_u64) = TryInto::<u64>::try_into(date).ok() {
-        date_as_u64_millis = _date_as_u64;
-    } else {
-        return Err(DispatchError { source: ConvertI64ToU64ForDate });
+/// Converts the date of `cli`, or the time of `clock` if it is missing or `now`, and shows it at
+/// the UTC offset or in the timezones of `cli`, listing the next runs of its schedule if any.
+fn report(cli: &Cli, clock: &dyn Clock) -> Result<Report, DispatchError> {
+    let timestamp = match cli.date.as_deref() {
+        Some(date) if date != "now" => date.parse()?,
+        _ => clock.now(),
+    };
+    let mut lines = vec![
+        timestamp.to_string(),
+        timestamp.as_millis().to_string(),
+        timestamp.as_moment()?.to_string(),
+        format!("secs {}, micros {}, nanos {}", timestamp.as_secs(), timestamp.as_micros()?, timestamp.as_nanos()?),
+        timestamp.to_rfc2822(),
+        timestamp.to_iso_week_date(),
+    ];
+    let timezones = match cli.timezones.as_deref() {
+        Some(offset) if offset.starts_with(['Z', '+', '-']) => {
+            lines.push(timestamp.as_datetime().with_timezone(&parse_utc_offset(offset)?).to_rfc3339());
+            return Ok(Report { timestamp, lines });
+        }
+        Some(timezones) => TimezoneFormatter::new(timezones.split(','))?,
+        None => return Ok(Report { timestamp, lines }),
+    };
+    for (timezone, local) in timezones.format(timestamp) {
+        lines.push(format!("{}: {}", timezone, local));
+    }
+    if let Some(expression) = &cli.schedule {
+        let timezone = timezones.timezones()[0];
+        for next in Schedule::parse(expression)?.next_n(timestamp, timezone, NEXT_RUNS) {
+            lines.push(format!("next run {}", next.in_timezone(timezone).format(TimezoneFormatter::DEFAULT_FORMAT)));
+        }
     }
-    return Ok(date_as_u64_millis);
+    Ok(Report { timestamp, lines })
 }
 
-fn main() {
-    ::std::env::set_var("RUST_LOG", "trace"); // or `RUST_LOG=trace cargo run`
-    log::set_max_level(log::LevelFilter::Debug);
-    env_logger::init();
-    info!("{:?}", log::max_level());
-    let utc: DateTime<Utc> = Utc::now();
-    let now: String = utc.to_rfc3339();
-    let utc_millis: Date = utc.timestamp_millis();
-    if log_enabled!(log::Level::Info) {
-        info!("{:?}", utc_millis);
-    }
-    let _requested_date_as_u64 = convert_i64_to_u64_in_milliseconds(utc_millis.clone());
-    let requested_date_as_u64;
-    match _requested_date_as_u64 {
-        Err(_e) => {
-            error!("Unable to convert i64 to u64 in millis for timestamp {:?}", _e);
-            return ();
-        },
-        Ok(ref x) => {
-            requested_date_as_u64 = x;
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() { ExitCode::from(exit_code::USAGE) } else { ExitCode::SUCCESS };
+        }
+    };
+    let config = cli.log;
+    config.init(SystemClock).expect("no other logger is installed");
+    debug!("{:?}", cli);
+    match report(&cli, &SystemClock) {
+        Ok(Report { timestamp, lines }) => {
+            for line in lines {
+                info!(utc_millis = timestamp.as_millis(); "{}", line);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) if config.format == LogFormat::Json => {
+            let causes: Vec<String> = chain(&e).map(|cause| cause.to_string()).collect();
+            let fields = [("exit_code", e.exit_code())];
+            // Written past the logger, so the error shows even with logging turned off.
+            eprintln!(
+                "{}",
+                format_record(
+                    LogFormat::Json,
+                    &Record::builder()
+                        .level(Level::Error)
+                        .target(module_path!())
+                        .args(format_args!("{}", causes.join(": ")))
+                        .key_values(&fields)
+                        .build(),
+                    SystemClock.now(),
+                )
+            );
+            ExitCode::from(e.exit_code())
+        }
+        Err(e) => {
+            eprintln!("{}", render(&e));
+            ExitCode::from(e.exit_code())
         }
     }
-    debug!("{:?}", requested_date_as_u64);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datetime::{FixedClock, ManualClock};
+
+    fn at(rfc3339: &str) -> Timestamp {
+        rfc3339.parse().unwrap()
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::parse_from(std::iter::once(&"datetime").chain(args))
+    }
+
+    #[test]
+    fn reports_the_clock_time_without_a_date() {
+        let clock = FixedClock(at("2023-03-05T12:00:00.250Z"));
+
+        assert_eq!(report(&cli(&[]), &clock).unwrap().lines, vec![
+            "2023-03-05T12:00:00.250Z",
+            "1678017600250",
+            "1678017600250",
+            "secs 1678017600, micros 1678017600250000, nanos 1678017600250000000",
+            "Sun, 05 Mar 2023 12:00:00 +0000",
+            "2023-W09-7",
+        ]);
+    }
+
+    #[test]
+    fn reports_the_given_date_instead_of_the_clock() {
+        let clock = FixedClock(Timestamp::unix_epoch());
+        let lines = report(&cli(&["2023-03-05T12:00:00Z", "+05:30"]), &clock).unwrap().lines;
+
+        assert_eq!(lines[0], "2023-03-05T12:00:00Z");
+        assert_eq!(lines.last().unwrap(), "2023-03-05T17:30:00+05:30");
+        let lines = report(&cli(&["2023-03-05T12:00:00Z", "-03:00"]), &clock).unwrap().lines;
+        assert_eq!(lines.last().unwrap(), "2023-03-05T09:00:00-03:00");
+    }
+
+    #[test]
+    fn lists_next_runs_from_the_clock_time() {
+        let clock = ManualClock::new(at("2023-03-25T12:00:00Z"));
+        let next_run = |clock: &ManualClock| {
+            report(&cli(&["now", "Europe/Berlin", "30 2 * * *"]), clock).unwrap().lines[7].clone()
+        };
+
+        assert_eq!(next_run(&clock), "next run 2023-03-26 03:00:00 CEST (+02:00)");
+        clock.advance(chrono::Duration::days(1)).unwrap();
+        assert_eq!(next_run(&clock), "next run 2023-03-27 02:30:00 CEST (+02:00)");
+    }
+
+    #[test]
+    fn reports_errors_with_their_exit_code() {
+        let clock = FixedClock(Timestamp::unix_epoch());
+        let err = report(&cli(&["1969-12-31T23:59:59Z"]), &clock).unwrap_err();
+
+        assert_eq!(err.exit_code(), exit_code::DATA);
+    }
+}
@@ -0,0 +1,292 @@
+//! Cron-like recurring schedules evaluated in an IANA timezone.
+//!
+//! A schedule has the five fields of a crontab line, `minute hour day-of-month month day-of-week`,
+//! each a `*`, a number, a range `a-b` or a comma separated list of those, optionally followed by
+//! a step `/n`. Days of the week run from 0 (Sunday) to 7 (Sunday again). As in Vixie cron, a day
+//! matches if either the day of month or the day of week matches when both are restricted.
+//!
+//! Times are local to the timezone the schedule is evaluated in. A local time that is skipped by
+//! a DST transition fires at the first minute after the gap, and one that occurs twice fires only
+//! at its first occurrence.
+
+use std::collections::VecDeque;
+
+use chrono::{Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::error::{DateError, DispatchError};
+use crate::Timestamp;
+
+/// How far past the last occurrence to look for the next matching day before giving up, e.g. on
+/// `0 0 30 2 *`. Covers the 28 year cycle after which dates fall on the same weekdays again.
+const MAX_DAYS: u32 = 28 * 366;
+
+/// The values one field of a schedule matches, as a bit per value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    bits: u64,
+    /// Whether the field started with `*`, which matters for combining days of month and week.
+    any: bool,
+}
+
+impl Field {
+    fn parse(field: &str, name: &'static str, min: u32, max: u32) -> Result<Self, String> {
+        let mut bits = 0;
+        for item in field.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => match step.parse::<u32>() {
+                    Ok(step) if step > 0 => (range, Some(step)),
+                    _ => return Err(format!("invalid step {:?} in {} field", step, name)),
+                },
+                None => (item, None),
+            };
+            let value = |value: &str| match value.parse::<u32>() {
+                Ok(value) if (min..=max).contains(&value) => Ok(value),
+                _ => Err(format!("{} must be between {} and {}, found {:?}", name, min, max, value)),
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` is short for `5-59/15`.
+                None if step.is_some() => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            };
+            if start > end {
+                return Err(format!("range {:?} in {} field is reversed", range, name));
+            }
+            for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(Field { bits, any: field.starts_with('*') })
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+
+    fn values(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..64).filter(move |&value| self.contains(value))
+    }
+}
+
+/// A parsed cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: Field,
+    hours: Field,
+    days_of_month: Field,
+    months: Field,
+    days_of_week: Field,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self, DispatchError> {
+        let invalid = |reason| DateError::InvalidSchedule { expression: expression.to_string(), reason };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(invalid(format!("expected 5 fields, found {}", fields.len())).into());
+        };
+        let mut days_of_week = Field::parse(days_of_week, "day of week", 0, 7).map_err(invalid)?;
+        if days_of_week.contains(7) {
+            days_of_week.bits |= 1;
+        }
+        Ok(Schedule {
+            minutes: Field::parse(minutes, "minute", 0, 59).map_err(invalid)?,
+            hours: Field::parse(hours, "hour", 0, 23).map_err(invalid)?,
+            days_of_month: Field::parse(days_of_month, "day of month", 1, 31).map_err(invalid)?,
+            months: Field::parse(months, "month", 1, 12).map_err(invalid)?,
+            days_of_week,
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !self.months.contains(date.month()) {
+            return false;
+        }
+        let day_of_month = self.days_of_month.contains(date.day());
+        let day_of_week = self.days_of_week.contains(date.weekday().num_days_from_sunday());
+        match (self.days_of_month.any, self.days_of_week.any) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+
+    /// Occurrences strictly after `after`, in `timezone`, in ascending order.
+    pub fn occurrences(&self, after: Timestamp, timezone: Tz) -> Occurrences<'_> {
+        // Start a day early, the first times of a day can be pushed past midnight by a DST gap.
+        let date = after.in_timezone(timezone).date_naive().pred_opt().unwrap_or(NaiveDate::MIN);
+        Occurrences { schedule: self, timezone, after, date: Some(date), pending: VecDeque::new(), days_left: MAX_DAYS }
+    }
+
+    /// The next `n` occurrences after `after`, fewer if the schedule stops matching.
+    pub fn next_n(&self, after: Timestamp, timezone: Tz, n: usize) -> Vec<Timestamp> {
+        self.occurrences(after, timezone).take(n).collect()
+    }
+}
+
+/// Iterator over the occurrences of a [`Schedule`], see [`Schedule::occurrences`].
+#[derive(Debug, Clone)]
+pub struct Occurrences<'a> {
+    schedule: &'a Schedule,
+    timezone: Tz,
+    after: Timestamp,
+    date: Option<NaiveDate>,
+    pending: VecDeque<Timestamp>,
+    days_left: u32,
+}
+
+impl Occurrences<'_> {
+    fn resolve(&self, local: NaiveDateTime) -> Option<Timestamp> {
+        match self.timezone.from_local_datetime(&local) {
+            LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => Some(datetime.into()),
+            LocalResult::None => (1..=24 * 60)
+                .find_map(|minutes| self.timezone.from_local_datetime(&(local + Duration::minutes(minutes))).earliest())
+                .map(Timestamp::from),
+        }
+    }
+
+    fn schedule_day(&mut self, date: NaiveDate) {
+        if !self.schedule.matches_day(date) {
+            return;
+        }
+        let mut times: Vec<Timestamp> = self
+            .schedule
+            .hours
+            .values()
+            .flat_map(|hour| self.schedule.minutes.values().map(move |minute| (hour, minute)))
+            .filter_map(|(hour, minute)| self.resolve(date.and_hms_opt(hour, minute, 0)?))
+            .collect();
+        times.sort();
+        times.dedup();
+        self.pending.extend(times);
+    }
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = Timestamp;
+
+    fn next(&mut self) -> Option<Timestamp> {
+        loop {
+            if let Some(next) = self.pending.pop_front() {
+                if next > self.after {
+                    self.after = next;
+                    self.days_left = MAX_DAYS;
+                    return Some(next);
+                }
+                continue;
+            }
+            let date = self.date?;
+            if self.days_left == 0 {
+                return None;
+            }
+            self.days_left -= 1;
+            self.date = date.succ_opt();
+            self.schedule_day(date);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{America::New_York, Asia::Singapore, Europe::Berlin, UTC};
+
+    fn at(rfc3339: &str) -> Timestamp {
+        rfc3339.parse().unwrap()
+    }
+
+    fn next(expression: &str, after: &str, timezone: Tz, n: usize) -> Vec<String> {
+        Schedule::parse(expression).unwrap().next_n(at(after), timezone, n).iter().map(Timestamp::to_rfc3339).collect()
+    }
+
+    #[test]
+    fn yields_the_next_occurrences() {
+        assert_eq!(next("*/15 9-10 * * *", "2023-03-05T09:40:00Z", UTC, 4), vec![
+            "2023-03-05T09:45:00Z",
+            "2023-03-05T10:00:00Z",
+            "2023-03-05T10:15:00Z",
+            "2023-03-05T10:30:00Z",
+        ]);
+        // Occurrences are strictly after the given time.
+        assert_eq!(next("0 12 * * *", "2023-03-05T12:00:00Z", UTC, 1), vec!["2023-03-06T12:00:00Z"]);
+    }
+
+    #[test]
+    fn evaluates_local_time_in_each_timezone() {
+        // Every Tuesday at 16:00 local time, for a release calendar spanning three offices.
+        assert_eq!(next("0 16 * * 2", "2023-03-05T00:00:00Z", Berlin, 1), vec!["2023-03-07T15:00:00Z"]);
+        assert_eq!(next("0 16 * * 2", "2023-03-05T00:00:00Z", New_York, 1), vec!["2023-03-07T21:00:00Z"]);
+        assert_eq!(next("0 16 * * 2", "2023-03-05T00:00:00Z", Singapore, 1), vec!["2023-03-07T08:00:00Z"]);
+    }
+
+    #[test]
+    fn combines_day_of_month_and_week_like_cron() {
+        // The 1st of the month or any Sunday.
+        assert_eq!(next("0 0 1 * 0", "2023-03-20T00:00:00Z", UTC, 3), vec![
+            "2023-03-26T00:00:00Z",
+            "2023-04-01T00:00:00Z",
+            "2023-04-02T00:00:00Z",
+        ]);
+        // Sunday as 7, every Sunday in March: the 26th is the last one of 2023.
+        assert_eq!(next("0 0 * 3 7", "2023-03-20T00:00:00Z", UTC, 2), vec!["2023-03-26T00:00:00Z", "2024-03-03T00:00:00Z"]);
+        assert_eq!(next("0 0 * 3 7", "2024-03-03T00:00:00Z", UTC, 2), vec!["2024-03-10T00:00:00Z", "2024-03-17T00:00:00Z"]);
+        assert_eq!(next("0 0 29 2 *", "2023-01-01T00:00:00Z", UTC, 1), vec!["2024-02-29T00:00:00Z"]);
+    }
+
+    #[test]
+    fn keeps_going_past_the_search_horizon() {
+        let daily = Schedule::parse("0 0 * * *").unwrap();
+        let last = daily.occurrences(at("2000-01-01T00:00:00Z"), UTC).nth(2 * MAX_DAYS as usize);
+
+        assert_eq!(last.map(|timestamp| timestamp.to_rfc3339()).as_deref(), Some("2056-02-13T00:00:00Z"));
+    }
+
+    #[test]
+    fn stops_on_days_that_never_come() {
+        assert!(next("0 0 30 2 *", "2023-01-01T00:00:00Z", UTC, 1).is_empty());
+    }
+
+    #[test]
+    fn fires_after_the_gap_when_clocks_spring_forward() {
+        // Berlin skips 02:00-03:00 CET on 2023-03-26, so 02:30 fires at 03:00 CEST.
+        assert_eq!(next("30 2 * * *", "2023-03-25T00:00:00Z", Berlin, 3), vec![
+            "2023-03-25T01:30:00Z",
+            "2023-03-26T01:00:00Z",
+            "2023-03-27T00:30:00Z",
+        ]);
+        // New York skips 02:00-03:00 EST on 2023-03-12; 03:00 is not scheduled twice.
+        assert_eq!(next("0 2,3 * * *", "2023-03-12T00:00:00Z", New_York, 3), vec![
+            "2023-03-12T07:00:00Z",
+            "2023-03-13T06:00:00Z",
+            "2023-03-13T07:00:00Z",
+        ]);
+    }
+
+    #[test]
+    fn fires_once_when_clocks_fall_back() {
+        // Berlin repeats 02:00-03:00 on 2023-10-29, first in CEST then in CET.
+        assert_eq!(next("30 2 * * *", "2023-10-28T00:00:00Z", Berlin, 3), vec![
+            "2023-10-28T00:30:00Z",
+            "2023-10-29T00:30:00Z",
+            "2023-10-30T01:30:00Z",
+        ]);
+        // Every hour keeps its UTC spacing apart from the repeated hour.
+        assert_eq!(next("0 * * * *", "2023-10-28T23:30:00Z", Berlin, 3), vec![
+            "2023-10-29T00:00:00Z",
+            "2023-10-29T02:00:00Z",
+            "2023-10-29T03:00:00Z",
+        ]);
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for invalid in ["* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(
+                matches!(Schedule::parse(invalid).unwrap_err().kind(), DateError::InvalidSchedule { .. }),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
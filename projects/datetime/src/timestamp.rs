@@ -0,0 +1,273 @@
+//! A point in time that converts between the units and formats chain timestamps come in.
+//!
+//! The runtime's `pallet_timestamp` counts Unix milliseconds as `u64`, while most tooling talks
+//! Unix seconds, and logs use RFC 3339 or RFC 2822. [`Timestamp`] holds the point in time once and
+//! converts to and from each of these, returning a [`DispatchError`] instead of panicking or
+//! wrapping around when a value does not fit.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+
+use crate::error::{ConvertI64ToU64ForDate, DateError, DispatchError};
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// A UTC point in time with nanosecond precision, within the range `chrono` supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(DateTime<Utc>);
+
+impl Timestamp {
+    pub fn unix_epoch() -> Self {
+        Timestamp(Utc.timestamp_opt(0, 0).unwrap())
+    }
+
+    pub fn from_datetime<Tz: TimeZone>(datetime: &DateTime<Tz>) -> Self {
+        Timestamp(datetime.with_timezone(&Utc))
+    }
+
+    pub fn from_secs(secs: i64) -> Result<Self, DispatchError> {
+        Self::from_nanos_i128(i128::from(secs) * NANOS_PER_SEC, secs, "s")
+    }
+
+    pub fn from_millis(millis: i64) -> Result<Self, DispatchError> {
+        Self::from_nanos_i128(i128::from(millis) * 1_000_000, millis, "ms")
+    }
+
+    pub fn from_micros(micros: i64) -> Result<Self, DispatchError> {
+        Self::from_nanos_i128(i128::from(micros) * 1_000, micros, "us")
+    }
+
+    /// Every `i64` of nanoseconds is a valid timestamp, between the years 1677 and 2262.
+    pub fn from_nanos(nanos: i64) -> Self {
+        Timestamp(Utc.timestamp_nanos(nanos))
+    }
+
+    /// A `pallet_timestamp` moment, in Unix milliseconds.
+    pub fn from_moment(moment: u64) -> Result<Self, DispatchError> {
+        let millis = i64::try_from(moment).map_err(|_| DateError::MomentOutOfRange { moment })?;
+        Self::from_millis(millis)
+    }
+
+    fn from_nanos_i128(nanos: i128, timestamp: i64, unit: &'static str) -> Result<Self, DispatchError> {
+        let secs = nanos.div_euclid(NANOS_PER_SEC);
+        let subsec_nanos = nanos.rem_euclid(NANOS_PER_SEC) as u32;
+        i64::try_from(secs)
+            .ok()
+            .and_then(|secs| Utc.timestamp_opt(secs, subsec_nanos).single())
+            .map(Timestamp)
+            .ok_or_else(|| DateError::OutOfRange { timestamp, unit }.into())
+    }
+
+    fn as_nanos_i128(&self) -> i128 {
+        i128::from(self.0.timestamp()) * NANOS_PER_SEC + i128::from(self.0.timestamp_subsec_nanos())
+    }
+
+    fn as_unit(&self, nanos_per_unit: i128, unit: &'static str) -> Result<i64, DispatchError> {
+        i64::try_from(self.as_nanos_i128().div_euclid(nanos_per_unit))
+            .map_err(|_| DateError::UnitOverflow { timestamp: *self, unit }.into())
+    }
+
+    /// Whole Unix seconds, rounded down. Every timestamp fits.
+    pub fn as_secs(&self) -> i64 {
+        self.0.timestamp()
+    }
+
+    /// Whole Unix milliseconds, rounded down. Every timestamp fits.
+    pub fn as_millis(&self) -> i64 {
+        self.as_nanos_i128().div_euclid(1_000_000) as i64
+    }
+
+    /// Whole Unix microseconds, rounded down.
+    pub fn as_micros(&self) -> Result<i64, DispatchError> {
+        self.as_unit(1_000, "us")
+    }
+
+    pub fn as_nanos(&self) -> Result<i64, DispatchError> {
+        self.as_unit(1, "ns")
+    }
+
+    /// The `pallet_timestamp` moment, which cannot be before the Unix epoch.
+    pub fn as_moment(&self) -> Result<u64, DispatchError> {
+        let millis = self.as_millis();
+        u64::try_from(millis).map_err(|_| {
+            DateError::NegativeTimestamp { timestamp: millis, source: ConvertI64ToU64ForDate }.into()
+        })
+    }
+
+    pub fn as_datetime(&self) -> DateTime<Utc> {
+        self.0
+    }
+
+    pub fn parse_rfc3339(input: &str) -> Result<Self, DispatchError> {
+        DateTime::parse_from_rfc3339(input).map(|datetime| Self::from_datetime(&datetime)).map_err(|source| {
+            DateError::Parse { input: input.to_string(), format: "an RFC 3339 date", source }.into()
+        })
+    }
+
+    pub fn parse_rfc2822(input: &str) -> Result<Self, DispatchError> {
+        DateTime::parse_from_rfc2822(input).map(|datetime| Self::from_datetime(&datetime)).map_err(|source| {
+            DateError::Parse { input: input.to_string(), format: "an RFC 2822 date", source }.into()
+        })
+    }
+
+    /// Parses an ISO 8601 week date such as `2023-W09-7` as midnight UTC of that day.
+    pub fn parse_iso_week_date(input: &str) -> Result<Self, DispatchError> {
+        let invalid = || DateError::InvalidWeekDate { input: input.to_string() };
+        let (year, rest) = input.rsplit_once("-W").ok_or_else(invalid)?;
+        let (week, weekday) = rest.split_once('-').ok_or_else(invalid)?;
+        let year: i32 = year.parse().map_err(|_| invalid())?;
+        let week: u32 = week.parse().map_err(|_| invalid())?;
+        let weekday = match weekday {
+            "1" => Weekday::Mon,
+            "2" => Weekday::Tue,
+            "3" => Weekday::Wed,
+            "4" => Weekday::Thu,
+            "5" => Weekday::Fri,
+            "6" => Weekday::Sat,
+            "7" => Weekday::Sun,
+            _ => return Err(invalid().into()),
+        };
+        NaiveDate::from_isoywd_opt(year, week, weekday)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|datetime| Timestamp(Utc.from_utc_datetime(&datetime)))
+            .ok_or_else(|| invalid().into())
+    }
+
+    /// RFC 3339 with as many fractional digits as needed, e.g. `2023-03-05T12:00:00.250Z`.
+    pub fn to_rfc3339(&self) -> String {
+        self.0.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+    }
+
+    /// RFC 2822 with a two digit day, e.g. `Sun, 05 Mar 2023 12:00:00 +0000`. Written out rather
+    /// than with `DateTime::to_rfc2822`, whose day padding differs between chrono releases.
+    pub fn to_rfc2822(&self) -> String {
+        self.0.format("%a, %d %b %Y %H:%M:%S %z").to_string()
+    }
+
+    /// ISO 8601 week date, e.g. `2023-W09-7`. The week-based year can differ from the calendar
+    /// year around New Year.
+    pub fn to_iso_week_date(&self) -> String {
+        let week = self.0.iso_week();
+        format!("{:04}-W{:02}-{}", week.year(), week.week(), self.0.weekday().number_from_monday())
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Result<Self, DispatchError> {
+        self.0
+            .checked_add_signed(duration)
+            .map(Timestamp)
+            .ok_or_else(|| DateError::ArithmeticOverflow { timestamp: *self, duration }.into())
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Result<Self, DispatchError> {
+        self.0
+            .checked_sub_signed(duration)
+            .map(Timestamp)
+            .ok_or_else(|| DateError::ArithmeticOverflow { timestamp: *self, duration: -duration }.into())
+    }
+
+    /// Signed time from `earlier` to `self`.
+    pub fn duration_since(&self, earlier: Timestamp) -> Duration {
+        self.0.signed_duration_since(earlier.0)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_rfc3339())
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = DispatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_rfc3339(s)
+    }
+}
+
+impl<Tz: TimeZone> From<DateTime<Tz>> for Timestamp {
+    fn from(datetime: DateTime<Tz>) -> Self {
+        Self::from_datetime(&datetime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_between_units() {
+        let timestamp = Timestamp::from_millis(1_678_017_600_250).unwrap();
+
+        assert_eq!(timestamp.as_secs(), 1_678_017_600);
+        assert_eq!(timestamp.as_millis(), 1_678_017_600_250);
+        assert_eq!(timestamp.as_micros().unwrap(), 1_678_017_600_250_000);
+        assert_eq!(timestamp.as_nanos().unwrap(), 1_678_017_600_250_000_000);
+        assert_eq!(Timestamp::from_secs(1_678_017_600).unwrap(), Timestamp::from_nanos(1_678_017_600_000_000_000));
+        assert_eq!(Timestamp::from_micros(-1).unwrap().as_millis(), -1);
+    }
+
+    #[test]
+    fn moments_are_unsigned_millis() {
+        assert_eq!(Timestamp::from_moment(1_000).unwrap().as_secs(), 1);
+        assert_eq!(Timestamp::unix_epoch().as_moment().unwrap(), 0);
+        assert!(matches!(
+            Timestamp::from_millis(-1).unwrap().as_moment().unwrap_err().kind(),
+            DateError::NegativeTimestamp { timestamp: -1, .. }
+        ));
+        assert!(matches!(Timestamp::from_moment(u64::MAX).unwrap_err().kind(), DateError::MomentOutOfRange { .. }));
+    }
+
+    #[test]
+    fn rejects_values_outside_the_supported_range() {
+        assert!(matches!(Timestamp::from_secs(i64::MAX).unwrap_err().kind(), DateError::OutOfRange { unit: "s", .. }));
+        assert!(matches!(Timestamp::from_millis(i64::MIN).unwrap_err().kind(), DateError::OutOfRange { unit: "ms", .. }));
+
+        let far_future = Timestamp::from_secs(100_000_000_000).unwrap();
+        assert!(matches!(far_future.as_nanos().unwrap_err().kind(), DateError::UnitOverflow { unit: "ns", .. }));
+        assert!(far_future.as_micros().is_ok());
+    }
+
+    #[test]
+    fn formats_and_parses_rfc3339_and_rfc2822() {
+        let timestamp = Timestamp::from_millis(1_678_017_600_250).unwrap();
+
+        assert_eq!(timestamp.to_rfc3339(), "2023-03-05T12:00:00.250Z");
+        assert_eq!(timestamp.to_rfc2822(), "Sun, 05 Mar 2023 12:00:00 +0000");
+        assert_eq!("2023-03-05T13:00:00.250+01:00".parse::<Timestamp>().unwrap(), timestamp);
+        assert_eq!(Timestamp::parse_rfc2822("Sun, 05 Mar 2023 07:00:00 -0500").unwrap().as_secs(), 1_678_017_600);
+        assert!(matches!(Timestamp::parse_rfc2822("2023-03-05").unwrap_err().kind(), DateError::Parse { .. }));
+    }
+
+    #[test]
+    fn formats_and_parses_iso_week_dates() {
+        let sunday = Timestamp::parse_rfc3339("2023-03-05T12:00:00Z").unwrap();
+        let new_year = Timestamp::parse_rfc3339("2021-01-01T00:00:00Z").unwrap();
+
+        assert_eq!(sunday.to_iso_week_date(), "2023-W09-7");
+        assert_eq!(new_year.to_iso_week_date(), "2020-W53-5");
+        assert_eq!(Timestamp::parse_iso_week_date("2020-W53-5").unwrap(), new_year);
+        for invalid in ["2023-09-7", "2023-W54-1", "2023-W09-8", "year-W01-1"] {
+            assert!(
+                matches!(Timestamp::parse_iso_week_date(invalid).unwrap_err().kind(), DateError::InvalidWeekDate { .. }),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn arithmetic_is_checked() {
+        let epoch = Timestamp::unix_epoch();
+        let later = epoch.checked_add(Duration::milliseconds(6_000)).unwrap();
+
+        assert_eq!(later.as_moment().unwrap(), 6_000);
+        assert_eq!(later.duration_since(epoch), Duration::seconds(6));
+        assert_eq!(later.checked_sub(Duration::seconds(6)).unwrap(), epoch);
+
+        let max = Timestamp::from(DateTime::<Utc>::MAX_UTC);
+        assert!(matches!(max.checked_add(Duration::nanoseconds(1)).unwrap_err().kind(), DateError::ArithmeticOverflow { .. }));
+    }
+}
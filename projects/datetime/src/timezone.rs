@@ -0,0 +1,116 @@
+//! Renders timestamps in named IANA timezones.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::error::{DateError, DispatchError};
+use crate::Timestamp;
+
+/// Looks up an IANA timezone such as `Europe/Berlin`.
+pub fn parse_timezone(name: &str) -> Result<Tz, DispatchError> {
+    name.parse()
+        .map_err(|_| DateError::Timezone { timezone: name.to_string(), reason: "unknown IANA timezone" }.into())
+}
+
+impl Timestamp {
+    pub fn in_timezone(&self, timezone: Tz) -> DateTime<Tz> {
+        self.as_datetime().with_timezone(&timezone)
+    }
+}
+
+/// Formats a timestamp in each of a list of timezones, e.g. for a release calendar shared across
+/// offices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimezoneFormatter {
+    timezones: Vec<Tz>,
+    format: String,
+}
+
+impl TimezoneFormatter {
+    /// Local date, time, zone abbreviation and UTC offset, e.g. `2023-03-26 03:00:00 CEST (+02:00)`.
+    pub const DEFAULT_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S %Z (%:z)";
+
+    pub fn new<'a>(timezones: impl IntoIterator<Item = &'a str>) -> Result<Self, DispatchError> {
+        Ok(TimezoneFormatter {
+            timezones: timezones.into_iter().map(parse_timezone).collect::<Result<_, _>>()?,
+            format: Self::DEFAULT_FORMAT.to_string(),
+        })
+    }
+
+    /// Uses a `chrono` strftime format instead of [`Self::DEFAULT_FORMAT`], rejecting unknown
+    /// specifiers such as `%Q` up front since rendering them would panic.
+    pub fn with_format(mut self, format: &str) -> Result<Self, DispatchError> {
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(DateError::InvalidFormat { format: format.to_string() }.into());
+        }
+        self.format = format.to_string();
+        Ok(self)
+    }
+
+    pub fn timezones(&self) -> &[Tz] {
+        &self.timezones
+    }
+
+    /// The timestamp rendered in every timezone, in the order they were given.
+    pub fn format(&self, timestamp: Timestamp) -> Vec<(Tz, String)> {
+        self.timezones
+            .iter()
+            .map(|&timezone| (timezone, timestamp.in_timezone(timezone).format(&self.format).to_string()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> Timestamp {
+        rfc3339.parse().unwrap()
+    }
+
+    fn render(formatter: &TimezoneFormatter, rfc3339: &str) -> Vec<String> {
+        formatter.format(at(rfc3339)).into_iter().map(|(_, rendered)| rendered).collect()
+    }
+
+    #[test]
+    fn renders_in_every_timezone() {
+        let formatter = TimezoneFormatter::new(["Europe/Berlin", "America/New_York", "Asia/Singapore"]).unwrap();
+
+        assert_eq!(render(&formatter, "2023-03-05T12:00:00Z"), vec![
+            "2023-03-05 13:00:00 CET (+01:00)",
+            "2023-03-05 07:00:00 EST (-05:00)",
+            "2023-03-05 20:00:00 +08 (+08:00)",
+        ]);
+    }
+
+    #[test]
+    fn follows_dst_transitions() {
+        let berlin = TimezoneFormatter::new(["Europe/Berlin"]).unwrap().with_format("%H:%M:%S %Z").unwrap();
+
+        // Spring forward: 02:00 CET is followed by 03:00 CEST.
+        assert_eq!(render(&berlin, "2023-03-26T00:59:59Z"), vec!["01:59:59 CET"]);
+        assert_eq!(render(&berlin, "2023-03-26T01:00:00Z"), vec!["03:00:00 CEST"]);
+        // Fall back: 02:30 happens twice, first in CEST then in CET.
+        assert_eq!(render(&berlin, "2023-10-29T00:30:00Z"), vec!["02:30:00 CEST"]);
+        assert_eq!(render(&berlin, "2023-10-29T01:30:00Z"), vec!["02:30:00 CET"]);
+    }
+
+    #[test]
+    fn rejects_unknown_timezones() {
+        let err = TimezoneFormatter::new(["Europe/Berlin", "Mars/Olympus_Mons"]).unwrap_err();
+
+        assert!(matches!(err.kind(), DateError::Timezone { timezone, .. } if timezone == "Mars/Olympus_Mons"));
+    }
+
+    #[test]
+    fn rejects_invalid_formats() {
+        let formatter = TimezoneFormatter::new(["Europe/Berlin"]).unwrap();
+
+        for format in ["%Q", "%Y-%m-%d %", "%H:%M %-"] {
+            let err = formatter.clone().with_format(format).unwrap_err();
+            assert!(matches!(err.kind(), DateError::InvalidFormat { format: invalid } if invalid == format), "{}", format);
+        }
+        assert!(formatter.with_format("%d.%m.%Y %H:%M").is_ok());
+    }
+}
@@ -0,0 +1,43 @@
+// Benchmarks for merging allocation files into the endowed accounts.
+//
+// Run with `cargo bench`. Each benchmark merges an allocation of `n` accounts, half of which
+// collide with `n / 2` endowed accounts, so ns/iter should grow roughly tenfold between sizes.
+
+#![feature(test)]
+extern crate test;
+
+use endow::{merge_balances, Balance, MergeStrategy};
+use sp_runtime::AccountId32;
+use test::{black_box, Bencher};
+
+fn accounts(range: std::ops::Range<u64>) -> Vec<(AccountId32, Balance)> {
+  range
+    .map(|i| {
+      let mut bytes = [0u8; 32];
+      bytes[..8].copy_from_slice(&i.to_le_bytes());
+      (AccountId32::from(bytes), i as Balance)
+    })
+    .collect()
+}
+
+fn bench_merge(b: &mut Bencher, n: u64) {
+  let endowed = accounts(0..n / 2);
+  let balances = accounts(n / 4..n / 4 + n);
+
+  b.iter(|| merge_balances(black_box(endowed.clone()), black_box(balances.clone()), MergeStrategy::Overwrite));
+}
+
+#[bench]
+fn merge_1_000(b: &mut Bencher) {
+  bench_merge(b, 1_000);
+}
+
+#[bench]
+fn merge_10_000(b: &mut Bencher) {
+  bench_merge(b, 10_000);
+}
+
+#[bench]
+fn merge_100_000(b: &mut Bencher) {
+  bench_merge(b, 100_000);
+}
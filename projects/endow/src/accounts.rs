@@ -0,0 +1,189 @@
+use codec::Encode;
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::Ss58Codec;
+use sp_core::hashing::blake2_256;
+use sp_core::sr25519;
+use sp_runtime::AccountId32;
+
+use crate::{decode_address, parse_account, AllocationError, Balance, KeyInfo, Scheme, TokenFormat};
+
+/// Maximum number of signatories `pallet_multisig` accepts.
+pub const MAX_SIGNATORIES: usize = 100;
+
+/// A multisig account to endow, declared by its signatories and threshold.
+///
+/// ```json
+/// { "signatories": ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY", "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"],
+///   "threshold": 2, "balance": "1,000,000 DHX" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultisigEntry {
+  /// Hex public keys or SS58 addresses, in any order.
+  pub signatories: Vec<String>,
+  pub threshold: u16,
+  pub balance: String,
+}
+
+/// An account to endow, declared by the secret URI or address it is derived from.
+///
+/// ```json
+/// { "uri": "0x1f1e...//treasury/0", "scheme": "sr25519", "balance": "500 DHX" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DerivedEntry {
+  /// A mnemonic or `0x` seed followed by `//hard` and `/soft` junctions, or an SS58 address
+  /// followed by soft junctions only.
+  pub uri: String,
+  #[serde(default, with = "scheme_name")]
+  pub scheme: Scheme,
+  pub balance: String,
+}
+
+mod scheme_name {
+  use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+  use crate::Scheme;
+
+  pub fn serialize<S: Serializer>(scheme: &Scheme, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(scheme)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Scheme, D::Error> {
+    String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+  }
+}
+
+/// Account id of the multisig of `signatories` with `threshold`, as computed by
+/// `pallet_multisig::Pallet::multi_account_id`.
+///
+/// The signatories are sorted first, so their order does not matter.
+pub fn multi_account_id(signatories: &[AccountId32], threshold: u16) -> AccountId32 {
+  let mut signatories = signatories.to_vec();
+  signatories.sort();
+  let entropy = (b"modlpy/utilisuba", &signatories, threshold).using_encoded(blake2_256);
+  AccountId32::from(entropy)
+}
+
+/// Derives the account of a secret URI, or soft-derives from the sr25519 public key of an SS58
+/// address when the URI starts with one. The latter never needs a secret in the allocation file.
+pub fn derive_account(scheme: Scheme, uri: &str) -> Result<AccountId32, String> {
+  let base = uri.split('/').next().unwrap_or_default();
+  if !base.is_empty() && decode_address(base).is_ok() {
+    if scheme != Scheme::Sr25519 {
+      return Err(format!("{} keys cannot be derived from a public key", scheme));
+    }
+    return sr25519::Public::from_string(uri)
+      .map(|public| public.0.into())
+      .map_err(|_| "public keys only support soft junctions".to_string());
+  }
+  KeyInfo::from_suri(scheme, uri, None).map(|key| key.public).map_err(|e| e.to_string())
+}
+
+impl MultisigEntry {
+  pub fn parse(&self, index: usize, token: &TokenFormat) -> Result<(AccountId32, Balance), AllocationError> {
+    let invalid = |reason: String| AllocationError::InvalidMultisig { index, reason };
+    if self.signatories.len() < 2 || self.signatories.len() > MAX_SIGNATORIES {
+      return Err(invalid(format!("needs 2 to {} signatories, found {}", MAX_SIGNATORIES, self.signatories.len())));
+    }
+    if self.threshold == 0 || usize::from(self.threshold) > self.signatories.len() {
+      return Err(invalid(format!("threshold {} must be between 1 and the number of signatories", self.threshold)));
+    }
+    let mut signatories = self
+      .signatories
+      .iter()
+      .map(|signatory| {
+        parse_account(signatory).map_err(|reason| invalid(format!("invalid signatory {:?}: {}", signatory, reason)))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    signatories.sort();
+    if let Some(pair) = signatories.windows(2).find(|pair| pair[0] == pair[1]) {
+      return Err(invalid(format!("duplicate signatory {}", pair[0])));
+    }
+    let balance = token
+      .parse(&self.balance)
+      .map_err(|e| invalid(format!("invalid balance {:?}: {}", self.balance, e)))?;
+    Ok((multi_account_id(&signatories, self.threshold), balance))
+  }
+}
+
+impl DerivedEntry {
+  /// The URI may hold a secret, so it is left out of errors.
+  pub fn parse(&self, index: usize, token: &TokenFormat) -> Result<(AccountId32, Balance), AllocationError> {
+    let invalid = |reason: String| AllocationError::InvalidDerivedAccount { index, reason };
+    let account = derive_account(self.scheme, &self.uri).map_err(invalid)?;
+    let balance = token
+      .parse(&self.balance)
+      .map_err(|e| invalid(format!("invalid balance {:?}: {}", self.balance, e)))?;
+    Ok((account, balance))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use hex_literal::hex;
+
+  const ALICE: [u8; 32] = hex!["d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"];
+  const BOB: [u8; 32] = hex!["8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"];
+  const CHARLIE: [u8; 32] = hex!["90b5ab205c6974c9ea841be688864633dc9ca8a357843eeacf2314649965fe22"];
+
+  #[test]
+  fn multisig_account_matches_pallet() {
+    // blake2_256(b"modlpy/utilisuba" ++ compact(3) ++ sorted signatories ++ 2u16.to_le_bytes())
+    let expected = hex!["49daa32c7287890f38b7e1a8cd2961723d36d20baa0bf3b82e0c4bdda93b1c0a"];
+
+    assert_eq!(multi_account_id(&[ALICE.into(), BOB.into(), CHARLIE.into()], 2), expected.into());
+    assert_eq!(multi_account_id(&[CHARLIE.into(), ALICE.into(), BOB.into()], 2), expected.into());
+    assert_ne!(multi_account_id(&[ALICE.into(), BOB.into(), CHARLIE.into()], 3), expected.into());
+  }
+
+  #[test]
+  fn parses_multisig_entry() {
+    let entry = MultisigEntry {
+      signatories: vec![
+        "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty".into(),
+        hex::encode(ALICE),
+      ],
+      threshold: 2,
+      balance: "1,000 DHX".into(),
+    };
+    let (account, balance) = entry.parse(0, &TokenFormat::default()).unwrap();
+
+    assert_eq!(account, multi_account_id(&[ALICE.into(), BOB.into()], 2));
+    assert_eq!(balance, 1_000 * TokenFormat::default().unit().unwrap());
+  }
+
+  #[test]
+  fn rejects_invalid_multisigs() {
+    let entry = |signatories: &[[u8; 32]], threshold| MultisigEntry {
+      signatories: signatories.iter().map(hex::encode).collect(),
+      threshold,
+      balance: "1 planck".into(),
+    };
+    let token = TokenFormat::default();
+
+    for invalid in [entry(&[ALICE], 1), entry(&[ALICE, BOB], 0), entry(&[ALICE, BOB], 3), entry(&[ALICE, BOB, ALICE], 2)] {
+      assert!(matches!(invalid.parse(4, &token), Err(AllocationError::InvalidMultisig { index: 4, .. })), "{:?}", invalid);
+    }
+  }
+
+  #[test]
+  fn derives_from_secret_uri_and_address() {
+    assert_eq!(derive_account(Scheme::Sr25519, "//Alice").unwrap(), ALICE.into());
+
+    let soft = derive_account(Scheme::Sr25519, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY/treasury").unwrap();
+    assert_eq!(soft, derive_account(Scheme::Sr25519, "//Alice/treasury").unwrap());
+    assert!(derive_account(Scheme::Sr25519, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY//hard").is_err());
+    assert!(derive_account(Scheme::Ed25519, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY/soft").is_err());
+  }
+
+  #[test]
+  fn derived_errors_leave_out_the_uri() {
+    let entry = DerivedEntry { uri: "secret words that are not bip39".into(), scheme: Scheme::Sr25519, balance: "1 planck".into() };
+    let err = entry.parse(0, &TokenFormat::default()).unwrap_err().to_string();
+
+    assert!(!err.contains("secret words"), "{}", err);
+  }
+}
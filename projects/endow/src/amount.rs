@@ -0,0 +1,218 @@
+use crate::Balance;
+
+/// Decimals and symbol of the chain's native token, used to read and print human-readable amounts.
+///
+/// Every amount names its unit: `"8,750.5 DHX"` is a token amount and `"8750 planck"` a raw
+/// planck value. Amounts without a unit are rejected, unless `bare_planck` is set for allocation
+/// files written before token amounts were supported, in which case they are planck values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenFormat {
+  pub decimals: u8,
+  pub symbol: String,
+  /// Read amounts without a unit as planck values.
+  pub bare_planck: bool,
+}
+
+impl Default for TokenFormat {
+  fn default() -> Self {
+    TokenFormat { decimals: 18, symbol: String::from("DHX"), bare_planck: false }
+  }
+}
+
+/// Suffix of raw planck values.
+const PLANCK: &str = "planck";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AmountError {
+  #[error("amount is empty")]
+  Empty,
+  #[error("unknown unit {unit:?}, expected {expected:?} or \"planck\"")]
+  UnknownUnit { unit: String, expected: String },
+  #[error("amount has no unit, expected e.g. \"8,750.5 {symbol}\" or \"8750 planck\"")]
+  MissingUnit { symbol: String },
+  #[error("planck values cannot have decimal places")]
+  FractionalPlanck,
+  #[error("amount must only contain digits, thousands separators and a decimal point")]
+  InvalidDigits,
+  #[error("thousands separators must split the integer part into groups of three digits")]
+  MisplacedSeparator,
+  #[error("amount has more than {0} decimal places")]
+  TooManyDecimals(u8),
+  #[error("amount overflows the balance type")]
+  Overflow,
+}
+
+impl TokenFormat {
+  /// Planck per token.
+  pub fn unit(&self) -> Option<Balance> {
+    (10 as Balance).checked_pow(self.decimals.into())
+  }
+
+  /// Parses a token amount like `"8,750.5 DHX"` or a raw planck value like `"8750 planck"`.
+  pub fn parse(&self, amount: &str) -> Result<Balance, AmountError> {
+    let amount = amount.trim();
+    let (number, in_tokens) = match (amount.strip_suffix(self.symbol.as_str()), amount.strip_suffix(PLANCK)) {
+      (Some(number), _) if !self.symbol.is_empty() => (number.trim_end(), true),
+      (_, Some(number)) => (number.trim_end(), false),
+      _ => (amount, false),
+    };
+    let has_unit = number.len() != amount.len();
+    if number.is_empty() {
+      return Err(AmountError::Empty);
+    }
+    if let Some(start) = number.find(|c: char| c.is_alphabetic()) {
+      return Err(AmountError::UnknownUnit { unit: number[start..].trim().to_string(), expected: self.symbol.clone() });
+    }
+
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let grouped = integer.contains(',');
+    let integer = if grouped { ungroup(integer)? } else { integer.to_string() };
+    if integer.is_empty() || !is_digits(&integer) || !is_digits(fraction) {
+      return Err(AmountError::InvalidDigits);
+    }
+
+    if !has_unit && !self.bare_planck {
+      return Err(AmountError::MissingUnit { symbol: self.symbol.clone() });
+    }
+    if !in_tokens {
+      if number.contains('.') {
+        return Err(AmountError::FractionalPlanck);
+      }
+      return integer.parse().map_err(|_| AmountError::Overflow);
+    }
+
+    if fraction.len() > self.decimals.into() {
+      return Err(AmountError::TooManyDecimals(self.decimals));
+    }
+    let unit = self.unit().ok_or(AmountError::Overflow)?;
+    let whole: Balance = integer.parse().map_err(|_| AmountError::Overflow)?;
+    let fraction = format!("{:0<width$}", fraction, width = usize::from(self.decimals));
+    let fraction: Balance = if fraction.is_empty() { 0 } else { fraction.parse().map_err(|_| AmountError::Overflow)? };
+    whole.checked_mul(unit).and_then(|planck| planck.checked_add(fraction)).ok_or(AmountError::Overflow)
+  }
+
+  /// Formats `balance` as a token amount with thousands separators, e.g. `"8,750.5 DHX"`.
+  pub fn format(&self, balance: Balance) -> String {
+    let (whole, fraction) = match self.unit() {
+      Some(unit) => (balance / unit, balance % unit),
+      None => (0, balance),
+    };
+    let digits = whole.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+      if i > 0 && (digits.len() - i) % 3 == 0 {
+        grouped.push(',');
+      }
+      grouped.push(digit);
+    }
+    if fraction > 0 {
+      let fraction = format!("{:0>width$}", fraction, width = usize::from(self.decimals));
+      grouped.push('.');
+      grouped.push_str(fraction.trim_end_matches('0'));
+    }
+    if !self.symbol.is_empty() {
+      grouped.push(' ');
+      grouped.push_str(&self.symbol);
+    }
+    grouped
+  }
+}
+
+fn is_digits(s: &str) -> bool {
+  s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Removes thousands separators from `integer`, checking they are placed every three digits.
+fn ungroup(integer: &str) -> Result<String, AmountError> {
+  let mut groups = integer.split(',');
+  let first = groups.next().unwrap_or_default();
+  if first.is_empty() || first.len() > 3 {
+    return Err(AmountError::MisplacedSeparator);
+  }
+  let mut digits = first.to_string();
+  for group in groups {
+    if group.len() != 3 {
+      return Err(AmountError::MisplacedSeparator);
+    }
+    digits.push_str(group);
+  }
+  Ok(digits)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DHX: Balance = 1_000_000_000_000_000_000;
+
+  #[test]
+  fn parses_token_amounts() {
+    let token = TokenFormat::default();
+
+    assert_eq!(token.parse("8750 DHX"), Ok(8_750 * DHX));
+    assert_eq!(token.parse("8750DHX"), Ok(8_750 * DHX));
+    assert_eq!(token.parse("8,750.5 DHX"), Ok(8_750 * DHX + DHX / 2));
+    assert_eq!(token.parse(" 1,000,000 DHX "), Ok(1_000_000 * DHX));
+    assert_eq!(token.parse("0.000000000000000001 DHX"), Ok(1));
+    assert_eq!(token.parse("8750.0 DHX"), Ok(8_750 * DHX));
+  }
+
+  #[test]
+  fn parses_planck_values() {
+    let token = TokenFormat::default();
+
+    assert_eq!(token.parse("8750000000000000000000 planck"), Ok(8_750 * DHX));
+    assert_eq!(token.parse("8,750 planck"), Ok(8_750));
+    assert_eq!(token.parse("8750.0 planck"), Err(AmountError::FractionalPlanck));
+  }
+
+  #[test]
+  fn requires_a_unit() {
+    let token = TokenFormat::default();
+
+    // Without a unit, neither can be read as planck while the other is read as tokens.
+    for unitless in ["8750", "8750.0", "8,750"] {
+      assert_eq!(token.parse(unitless), Err(AmountError::MissingUnit { symbol: "DHX".into() }), "{}", unitless);
+    }
+
+    let bare_planck = TokenFormat { bare_planck: true, ..TokenFormat::default() };
+    assert_eq!(bare_planck.parse("8750"), Ok(8_750));
+    assert_eq!(bare_planck.parse("8750.0"), Err(AmountError::FractionalPlanck));
+    assert_eq!(bare_planck.parse("8750 DHX"), Ok(8_750 * DHX));
+  }
+
+  #[test]
+  fn rejects_malformed_amounts() {
+    let token = TokenFormat::default();
+
+    assert_eq!(token.parse(""), Err(AmountError::Empty));
+    assert_eq!(token.parse("DHX"), Err(AmountError::Empty));
+    assert_eq!(token.parse("5 DOT"), Err(AmountError::UnknownUnit { unit: "DOT".into(), expected: "DHX".into() }));
+    assert_eq!(token.parse("1_000"), Err(AmountError::InvalidDigits));
+    assert_eq!(token.parse("-5 DHX"), Err(AmountError::InvalidDigits));
+    assert_eq!(token.parse("1.2.3 DHX"), Err(AmountError::InvalidDigits));
+    assert_eq!(token.parse("87,50 DHX"), Err(AmountError::MisplacedSeparator));
+    assert_eq!(token.parse("8750,000 DHX"), Err(AmountError::MisplacedSeparator));
+    assert_eq!(token.parse("0.0000000000000000001 DHX"), Err(AmountError::TooManyDecimals(18)));
+    assert_eq!(token.parse("400000000000000000000 DHX"), Err(AmountError::Overflow));
+  }
+
+  #[test]
+  fn uses_configured_decimals_and_symbol() {
+    let token = TokenFormat { decimals: 10, symbol: "DOT".into(), bare_planck: false };
+
+    assert_eq!(token.parse("1.5 DOT"), Ok(15_000_000_000));
+    assert_eq!(token.format(15_000_000_000), "1.5 DOT");
+  }
+
+  #[test]
+  fn formats_with_separators() {
+    let token = TokenFormat::default();
+
+    assert_eq!(token.format(8_750 * DHX + DHX / 2), "8,750.5 DHX");
+    assert_eq!(token.format(138_750 * DHX), "138,750 DHX");
+    assert_eq!(token.format(0), "0 DHX");
+    assert_eq!(token.format(1), "0.000000000000000001 DHX");
+    assert_eq!(token.format(u128::MAX), "340,282,366,920,938,463,463.374607431768211455 DHX");
+  }
+}
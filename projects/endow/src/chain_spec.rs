@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use sp_runtime::AccountId32;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{parse_account, AllocationError, Balance, VestingSection};
+
+/// The `balances` genesis config of `pallet_balances`, as it appears in a chain spec under
+/// `genesis.runtime.balances`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalancesSection {
+  pub balances: Vec<(String, Balance)>,
+}
+
+impl BalancesSection {
+  /// Encodes every account as an SS58 address with the network `ss58_prefix`.
+  pub fn new(balances: &[(AccountId32, Balance)], ss58_prefix: u16) -> Self {
+    let format = Ss58AddressFormat::custom(ss58_prefix);
+    BalancesSection {
+      balances: balances
+        .iter()
+        .map(|(account, balance)| (account.to_ss58check_with_version(format), *balance))
+        .collect(),
+    }
+  }
+
+  /// Parses the addresses back into accounts, whatever their SS58 prefix.
+  pub fn parse(&self) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+    self.balances
+      .iter()
+      .enumerate()
+      .map(|(index, (account, balance))| {
+        let account_id = parse_account(account)
+          .map_err(|reason| AllocationError::InvalidAccount { index, account: account.clone(), reason })?;
+        Ok((account_id, *balance))
+      })
+      .collect()
+  }
+}
+
+/// The `balances` and `vesting` genesis configs side by side, as under `genesis.runtime`, written
+/// when the allocation has vesting schedules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisSections {
+  pub balances: BalancesSection,
+  pub vesting: VestingSection,
+}
+
+/// Order of the accounts in the generated section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+  /// Order in which accounts were first seen in the endowed accounts and then the allocation.
+  Input,
+  /// Ascending by public key, independent of the SS58 prefix.
+  Account,
+  /// Descending by balance, ties ascending by public key.
+  Balance,
+}
+
+impl SortOrder {
+  pub const NAMES: [&'static str; 3] = ["input", "account", "balance"];
+
+  pub fn sort(self, balances: &mut [(AccountId32, Balance)]) {
+    match self {
+      SortOrder::Input => (),
+      SortOrder::Account => balances.sort_by(|a, b| a.0.cmp(&b.0)),
+      SortOrder::Balance => balances.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))),
+    }
+  }
+}
+
+impl fmt::Display for SortOrder {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      SortOrder::Input => Self::NAMES[0],
+      SortOrder::Account => Self::NAMES[1],
+      SortOrder::Balance => Self::NAMES[2],
+    };
+    f.write_str(name)
+  }
+}
+
+impl FromStr for SortOrder {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "input" => Ok(SortOrder::Input),
+      "account" => Ok(SortOrder::Account),
+      "balance" => Ok(SortOrder::Balance),
+      _ => Err(format!("unknown sort order {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+    }
+  }
+}
+
+// Chain spec layouts the `balances` section is looked up in, from newest to oldest.
+#[derive(Deserialize)]
+struct PatchSpec {
+  genesis: PatchGenesis,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatchGenesis {
+  runtime_genesis: PatchRuntimeGenesis,
+}
+
+#[derive(Deserialize)]
+struct PatchRuntimeGenesis {
+  #[serde(alias = "config")]
+  patch: RuntimeConfig,
+}
+
+#[derive(Deserialize)]
+struct RuntimeSpec {
+  genesis: RuntimeGenesis,
+}
+
+#[derive(Deserialize)]
+struct RuntimeGenesis {
+  runtime: RuntimeConfig,
+}
+
+#[derive(Deserialize)]
+struct RuntimeConfig {
+  balances: BalancesSection,
+}
+
+/// Reads the `balances` section of a chain spec.
+///
+/// Accepts a full (non-raw) chain spec with the section under `genesis.runtime` or
+/// `genesis.runtimeGenesis.patch`, or the bare section or [`GenesisSections`] written by `endow`.
+pub fn read_spec_balances(path: impl AsRef<Path>) -> Result<BalancesSection, AllocationError> {
+  let path = path.as_ref();
+  let data = fs::read_to_string(path)
+    .map_err(|source| AllocationError::Open { path: path.to_path_buf(), source })?;
+  // Surface syntax errors with their line before trying the different layouts.
+  serde_json::from_str::<serde::de::IgnoredAny>(&data)?;
+
+  if let Ok(spec) = serde_json::from_str::<RuntimeSpec>(&data) {
+    return Ok(spec.genesis.runtime.balances);
+  }
+  if let Ok(spec) = serde_json::from_str::<PatchSpec>(&data) {
+    return Ok(spec.genesis.runtime_genesis.patch.balances);
+  }
+  if let Ok(sections) = serde_json::from_str::<RuntimeConfig>(&data) {
+    return Ok(sections.balances);
+  }
+  serde_json::from_str::<BalancesSection>(&data)
+    .map_err(|_| AllocationError::NoBalancesSection { path: path.to_path_buf() })
+}
+
+/// Differences between the balances in an existing spec and freshly generated ones.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpecDiff {
+  /// Generated accounts that the spec lacks.
+  pub missing: Vec<(AccountId32, Balance)>,
+  /// Accounts in the spec that were not generated.
+  pub unexpected: Vec<(AccountId32, Balance)>,
+  /// Accounts with a different balance, as `(account, in spec, generated)`.
+  pub changed: Vec<(AccountId32, Balance, Balance)>,
+  /// Accounts listed more than once in the spec, with every balance they are listed with. They
+  /// are left out of the other lists.
+  pub duplicated: Vec<(AccountId32, Vec<Balance>)>,
+}
+
+impl SpecDiff {
+  /// Compares accounts by public key, so the SS58 prefix and the order of entries do not matter.
+  pub fn new(spec: &[(AccountId32, Balance)], generated: &[(AccountId32, Balance)]) -> Self {
+    let mut spec_balances: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for (account, balance) in spec {
+      spec_balances.entry(account.clone()).or_default().push(*balance);
+    }
+    let generated_balances: BTreeMap<_, _> = generated.iter().cloned().collect();
+    let mut diff = SpecDiff::default();
+
+    for (account, generated) in &generated_balances {
+      match spec_balances.get(account).map(Vec::as_slice) {
+        None => diff.missing.push((account.clone(), *generated)),
+        Some([in_spec]) if in_spec != generated => diff.changed.push((account.clone(), *in_spec, *generated)),
+        Some(_) => (),
+      }
+    }
+    for (account, in_spec) in spec_balances {
+      match in_spec[..] {
+        [_, _, ..] => diff.duplicated.push((account, in_spec)),
+        [in_spec] if !generated_balances.contains_key(&account) => diff.unexpected.push((account, in_spec)),
+        _ => (),
+      }
+    }
+
+    diff
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.missing.is_empty() && self.unexpected.is_empty() && self.changed.is_empty() && self.duplicated.is_empty()
+  }
+}
+
+impl fmt::Display for SpecDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (account, balance) in &self.missing {
+      writeln!(f, "+ {} {}", account, balance)?;
+    }
+    for (account, balance) in &self.unexpected {
+      writeln!(f, "- {} {}", account, balance)?;
+    }
+    for (account, in_spec, generated) in &self.changed {
+      writeln!(f, "~ {} {} -> {}", account, in_spec, generated)?;
+    }
+    for (account, in_spec) in &self.duplicated {
+      let balances: Vec<_> = in_spec.iter().map(Balance::to_string).collect();
+      writeln!(f, "! {} listed {} times: {}", account, in_spec.len(), balances.join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn account(byte: u8) -> AccountId32 {
+    AccountId32::from([byte; 32])
+  }
+
+  #[test]
+  fn encodes_with_network_prefix() {
+    let balances = vec![(account(0xd4), 1)];
+
+    let polkadot = BalancesSection::new(&balances, 0);
+    let substrate = BalancesSection::new(&balances, 42);
+
+    assert!(polkadot.balances[0].0.starts_with('1'));
+    assert!(substrate.balances[0].0.starts_with('5'));
+    assert_eq!(polkadot.parse().unwrap(), balances);
+    assert_eq!(substrate.parse().unwrap(), balances);
+  }
+
+  #[test]
+  fn serializes_amounts_as_numbers() {
+    let section = BalancesSection::new(&[(account(1), u128::MAX)], 42);
+    let json = serde_json::to_string(&section).unwrap();
+
+    assert!(json.ends_with(&format!(",{}]]}}", u128::MAX)), "{}", json);
+    assert_eq!(serde_json::from_str::<BalancesSection>(&json).unwrap(), section);
+  }
+
+  #[test]
+  fn sorts_by_account_or_balance() {
+    let mut balances = vec![(account(2), 5), (account(3), 7), (account(1), 5)];
+
+    SortOrder::Account.sort(&mut balances);
+    assert_eq!(balances, vec![(account(1), 5), (account(2), 5), (account(3), 7)]);
+
+    SortOrder::Balance.sort(&mut balances);
+    assert_eq!(balances, vec![(account(3), 7), (account(1), 5), (account(2), 5)]);
+  }
+
+  #[test]
+  fn finds_balances_in_chain_spec_layouts() {
+    let dir = tempfile::tempdir().unwrap();
+    let address = BalancesSection::new(&[(account(1), 0)], 42).balances[0].0.clone();
+    let layouts = [
+      format!(r#"{{ "name": "Dev", "genesis": {{ "runtime": {{ "system": {{}}, "balances": {{ "balances": [["{}", 10]] }} }} }} }}"#, address),
+      format!(r#"{{ "genesis": {{ "runtimeGenesis": {{ "code": "0x00", "patch": {{ "balances": {{ "balances": [["{}", 10]] }} }} }} }} }}"#, address),
+      format!(r#"{{ "balances": {{ "balances": [["{}", 10]] }}, "vesting": {{ "vesting": [] }} }}"#, address),
+      format!(r#"{{ "balances": [["{}", 10]] }}"#, address),
+    ];
+
+    for (i, layout) in layouts.iter().enumerate() {
+      let path = dir.path().join(format!("{}.json", i));
+      fs::write(&path, layout).unwrap();
+      assert_eq!(read_spec_balances(&path).unwrap().parse().unwrap(), vec![(account(1), 10)], "{}", layout);
+    }
+
+    let raw = dir.path().join("raw.json");
+    fs::write(&raw, r#"{ "genesis": { "raw": { "top": {} } } }"#).unwrap();
+    assert!(matches!(read_spec_balances(&raw), Err(AllocationError::NoBalancesSection { .. })));
+  }
+
+  #[test]
+  fn diffs_by_public_key() {
+    let spec = vec![(account(1), 1), (account(2), 2), (account(4), 4)];
+    let generated = vec![(account(2), 20), (account(3), 3), (account(1), 1)];
+
+    let diff = SpecDiff::new(&spec, &generated);
+
+    assert_eq!(diff.missing, vec![(account(3), 3)]);
+    assert_eq!(diff.unexpected, vec![(account(4), 4)]);
+    assert_eq!(diff.changed, vec![(account(2), 2, 20)]);
+    assert!(SpecDiff::new(&generated, &generated).is_empty());
+  }
+
+  #[test]
+  fn reports_accounts_listed_twice_in_the_spec() {
+    let spec = vec![(account(1), 1), (account(2), 2), (account(1), 1), (account(3), 3), (account(3), 30)];
+    let generated = vec![(account(1), 1), (account(2), 2)];
+
+    let diff = SpecDiff::new(&spec, &generated);
+
+    assert_eq!(diff.duplicated, vec![(account(1), vec![1, 1]), (account(3), vec![3, 30])]);
+    assert!(diff.missing.is_empty() && diff.unexpected.is_empty() && diff.changed.is_empty());
+    assert!(!diff.is_empty());
+    assert!(diff.to_string().contains("listed 2 times: 3, 30"), "{}", diff);
+  }
+}
@@ -0,0 +1,202 @@
+use sp_runtime::AccountId32;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{AllocationError, AllocationSection, Balance};
+
+/// Limits the merged balances are checked against before they go into a genesis config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SanityChecks {
+  /// Minimum balance of every account, `ExistentialDeposit` of the runtime.
+  pub existential_deposit: Balance,
+  /// Total issuance the balances have to add up to, if known.
+  pub expected_total: Option<Balance>,
+}
+
+/// A single problem found by [`SanityChecks::check`], [`check_duplicates`] or
+/// [`genesis_vesting`](crate::genesis_vesting).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+  /// The sum of all balances does not fit into a `Balance`.
+  IssuanceOverflow,
+  ZeroBalance { index: usize, account: AccountId32 },
+  BelowExistentialDeposit { index: usize, account: AccountId32, balance: Balance, existential_deposit: Balance },
+  /// The account appears at every one of `entries`, given as a section and an index within it.
+  DuplicateAccount { account: AccountId32, entries: Vec<(&'static str, usize)> },
+  TotalMismatch { expected: Balance, actual: Balance },
+  /// A vesting schedule for an account without a balance.
+  VestingWithoutBalance { index: usize, account: AccountId32 },
+  VestingExceedsBalance { index: usize, account: AccountId32, locked: Balance, balance: Balance },
+  /// A vesting schedule that locks nothing or never unlocks.
+  ZeroVesting { index: usize, account: AccountId32 },
+  /// A vesting schedule whose begin or length does not fit a block number.
+  VestingTooLong { index: usize, account: AccountId32 },
+  DuplicateVesting { account: AccountId32, indices: Vec<usize> },
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Violation::IssuanceOverflow => write!(f, "total issuance overflows the balance type"),
+      Violation::ZeroBalance { index, account } => write!(f, "entry {}: {} has a zero balance", index, account),
+      Violation::BelowExistentialDeposit { index, account, balance, existential_deposit } => write!(
+        f,
+        "entry {}: {} has balance {} below the existential deposit {}",
+        index, account, balance, existential_deposit
+      ),
+      Violation::DuplicateAccount { account, entries } => {
+        let entries: Vec<_> = entries.iter().map(|(section, index)| format!("{} entry {}", section, index)).collect();
+        write!(f, "{} appears at {}", account, entries.join(", "))
+      }
+      Violation::TotalMismatch { expected, actual } => {
+        write!(f, "total issuance {} does not match the expected {}", actual, expected)
+      }
+      Violation::VestingWithoutBalance { index, account } => {
+        write!(f, "vesting entry {}: {} has no balance", index, account)
+      }
+      Violation::VestingExceedsBalance { index, account, locked, balance } => write!(
+        f,
+        "vesting entry {}: {} locks {}, more than its balance {}",
+        index, account, locked, balance
+      ),
+      Violation::ZeroVesting { index, account } => {
+        write!(f, "vesting entry {}: {} has a zero amount or per-block unlock", index, account)
+      }
+      Violation::VestingTooLong { index, account } => {
+        write!(f, "vesting entry {}: {} vests beyond the last block number", index, account)
+      }
+      Violation::DuplicateVesting { account, indices } => {
+        write!(f, "{} has vesting schedules at entries {:?}", account, indices)
+      }
+    }
+  }
+}
+
+impl SanityChecks {
+  /// Runs every check on the merged `balances` and returns the total issuance.
+  ///
+  /// All violations are collected and returned together in [`AllocationError::Sanity`], in entry
+  /// order with the issuance checks last. Repeated accounts are not looked for, the merge keeps one
+  /// balance per account; see [`check_duplicates`] for the entries of an allocation file.
+  pub fn check(&self, balances: &[(AccountId32, Balance)]) -> Result<Balance, AllocationError> {
+    let mut violations = vec![];
+    let mut total: Option<Balance> = Some(0);
+
+    for (index, (account, balance)) in balances.iter().enumerate() {
+      if *balance == 0 {
+        violations.push(Violation::ZeroBalance { index, account: account.clone() });
+      } else if *balance < self.existential_deposit {
+        violations.push(Violation::BelowExistentialDeposit {
+          index,
+          account: account.clone(),
+          balance: *balance,
+          existential_deposit: self.existential_deposit,
+        });
+      }
+      total = total.and_then(|total| total.checked_add(*balance));
+    }
+
+    match (total, self.expected_total) {
+      (None, _) => violations.push(Violation::IssuanceOverflow),
+      (Some(actual), Some(expected)) if actual != expected => {
+        violations.push(Violation::TotalMismatch { expected, actual })
+      }
+      _ => (),
+    }
+
+    match total {
+      Some(total) if violations.is_empty() => Ok(total),
+      _ => Err(AllocationError::Sanity(violations)),
+    }
+  }
+}
+
+/// Reports every account that appears more than once across the `sections` of an allocation file,
+/// with the entries it appears at in [`AllocationError::Sanity`].
+pub fn check_duplicates(sections: &[AllocationSection]) -> Result<(), AllocationError> {
+  let mut entries: HashMap<&AccountId32, Vec<(&'static str, usize)>> = HashMap::new();
+  let mut order = vec![];
+  for section in sections {
+    for (index, (account, _)) in section.balances.iter().enumerate() {
+      let account_entries = entries.entry(account).or_default();
+      if account_entries.is_empty() {
+        order.push(account);
+      }
+      account_entries.push((section.name, index));
+    }
+  }
+
+  let violations: Vec<_> = order
+    .into_iter()
+    .filter_map(|account| {
+      let entries = entries.remove(account)?;
+      (entries.len() > 1).then(|| Violation::DuplicateAccount { account: account.clone(), entries })
+    })
+    .collect();
+  if violations.is_empty() {
+    Ok(())
+  } else {
+    Err(AllocationError::Sanity(violations))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn account(byte: u8) -> AccountId32 {
+    AccountId32::from([byte; 32])
+  }
+
+  fn violations(checks: SanityChecks, balances: &[(AccountId32, Balance)]) -> Vec<Violation> {
+    match checks.check(balances) {
+      Err(AllocationError::Sanity(violations)) => violations,
+      other => panic!("expected violations, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn returns_total_issuance() {
+    let checks = SanityChecks { existential_deposit: 5, expected_total: Some(15) };
+
+    assert_eq!(checks.check(&[(account(1), 5), (account(2), 10)]).unwrap(), 15);
+  }
+
+  #[test]
+  fn reports_all_violations_together() {
+    let checks = SanityChecks { existential_deposit: 5, expected_total: Some(100) };
+    let balances = vec![(account(1), 0), (account(2), 4), (account(3), 10), (account(4), 6)];
+
+    assert_eq!(violations(checks, &balances), vec![
+      Violation::ZeroBalance { index: 0, account: account(1) },
+      Violation::BelowExistentialDeposit { index: 1, account: account(2), balance: 4, existential_deposit: 5 },
+      Violation::TotalMismatch { expected: 100, actual: 20 },
+    ]);
+  }
+
+  #[test]
+  fn reports_duplicates_by_section_index() {
+    let sections = [
+      AllocationSection { name: "balances", balances: vec![(account(1), 0), (account(2), 1), (account(1), 2)] },
+      AllocationSection { name: "multisigs", balances: vec![(account(3), 3)] },
+      AllocationSection { name: "derived", balances: vec![(account(2), 4), (account(4), 5)] },
+    ];
+
+    match check_duplicates(&sections).unwrap_err() {
+      AllocationError::Sanity(violations) => assert_eq!(violations, vec![
+        Violation::DuplicateAccount { account: account(1), entries: vec![("balances", 0), ("balances", 2)] },
+        Violation::DuplicateAccount { account: account(2), entries: vec![("balances", 1), ("derived", 0)] },
+      ]),
+      err => panic!("unexpected error {:?}", err),
+    }
+    assert!(check_duplicates(&sections[1..]).is_ok());
+  }
+
+  #[test]
+  fn reports_issuance_overflow_instead_of_mismatch() {
+    let checks = SanityChecks { existential_deposit: 0, expected_total: Some(1) };
+    let balances = vec![(account(1), Balance::MAX), (account(2), 1)];
+
+    assert_eq!(violations(checks, &balances), vec![Violation::IssuanceOverflow]);
+  }
+}
@@ -0,0 +1,142 @@
+use std::io;
+use std::path::PathBuf;
+
+use sp_runtime::AccountId32;
+
+use crate::{AmountError, Balance, Conflict, Violation};
+
+/// Everything that can go wrong while loading a genesis allocation.
+///
+/// Variants about a single entry carry its index in the `balances` array, or its line for CSV, so
+/// the offending line of a large allocation file can be found quickly.
+#[derive(Debug, thiserror::Error)]
+pub enum AllocationError {
+  #[error("cannot open allocation file {}: {source}", path.display())]
+  Open {
+    path: PathBuf,
+    #[source]
+    source: io::Error,
+  },
+  #[error("cannot write {}: {source}", path.display())]
+  Write {
+    path: PathBuf,
+    #[source]
+    source: io::Error,
+  },
+  #[error("cannot read allocation: {0}")]
+  Read(#[source] io::Error),
+  #[error("invalid allocation JSON at line {line}, column {column}: {source}")]
+  Json {
+    line: usize,
+    column: usize,
+    #[source]
+    source: serde_json::Error,
+  },
+  #[error("unknown allocation format for {}, expected a .json, .csv, .scale or .bin file", path.display())]
+  UnknownFormat { path: PathBuf },
+  #[error("invalid allocation CSV at line {line}: {source}")]
+  Csv {
+    line: u64,
+    #[source]
+    source: csv::Error,
+  },
+  #[error("invalid allocation CSV at line {line}: expected 2 columns (account, balance), found {columns}")]
+  CsvColumns { line: u64, columns: usize },
+  #[error("invalid allocation CSV at line {line}: invalid account {account:?}: {reason}")]
+  InvalidCsvAccount {
+    line: u64,
+    account: String,
+    reason: &'static str,
+  },
+  #[error("invalid allocation CSV at line {line}: invalid balance {balance:?}: {source}")]
+  InvalidCsvBalance {
+    line: u64,
+    balance: String,
+    #[source]
+    source: AmountError,
+  },
+  #[error("invalid SCALE-encoded allocation: {0}")]
+  Scale(#[from] codec::Error),
+  #[error("invalid SCALE-encoded allocation: {0} trailing bytes")]
+  ScaleTrailingBytes(usize),
+  #[error("entry {index}: invalid account {account:?}: {reason}")]
+  InvalidAccount {
+    index: usize,
+    account: String,
+    reason: &'static str,
+  },
+  #[error("entry {index}: invalid balance {balance:?}: {source}")]
+  InvalidBalance {
+    index: usize,
+    balance: String,
+    #[source]
+    source: AmountError,
+  },
+  #[error("multisig entry {index}: {reason}")]
+  InvalidMultisig { index: usize, reason: String },
+  #[error("derived account entry {index}: {reason}")]
+  InvalidDerivedAccount { index: usize, reason: String },
+  #[error("vesting entry {index}: invalid account {account:?}: {reason}")]
+  InvalidVestingAccount {
+    index: usize,
+    account: String,
+    reason: &'static str,
+  },
+  #[error("vesting entry {index}: invalid {field} {amount:?}: {source}")]
+  InvalidVestingAmount {
+    index: usize,
+    field: &'static str,
+    amount: String,
+    #[source]
+    source: AmountError,
+  },
+  #[error("snapshot entry {index}: {reason}")]
+  InvalidSnapshotEntry { index: usize, reason: &'static str },
+  #[error("invalid secret URI: {0}")]
+  InvalidSecretUri(&'static str),
+  #[error("invalid public key {public_key:?}: {reason}")]
+  InvalidPublicKey { public_key: String, reason: &'static str },
+  #[error("invalid SS58 address {0:?}")]
+  InvalidAddress(String),
+  #[error("--{option} {amount:?}: {source}")]
+  InvalidAmount {
+    option: &'static str,
+    amount: String,
+    #[source]
+    source: AmountError,
+  },
+  #[error("{account}: endowed {endowed} plus allocated {allocated} overflows the balance type")]
+  BalanceOverflow {
+    account: AccountId32,
+    endowed: Balance,
+    allocated: Balance,
+  },
+  #[error("{account}: the summed balances of its repeated rows overflow the balance type")]
+  DuplicateOverflow { account: AccountId32 },
+  #[error("{} accounts appear more than once in the {input}, collapse them with --dedup:{}", accounts.len(), account_list(accounts))]
+  DuplicateAccounts { input: &'static str, accounts: Vec<AccountId32> },
+  #[error("{} accounts have conflicting balances:{}", .0.len(), conflict_list(.0))]
+  Conflicts(Vec<Conflict>),
+  #[error("{} sanity checks failed:{}", .0.len(), violation_list(.0))]
+  Sanity(Vec<Violation>),
+  #[error("no balances section in {}, raw chain specs are not supported", path.display())]
+  NoBalancesSection { path: PathBuf },
+}
+
+fn account_list(accounts: &[AccountId32]) -> String {
+  accounts.iter().map(|account| format!("\n  {}", account)).collect()
+}
+
+fn conflict_list(conflicts: &[Conflict]) -> String {
+  conflicts.iter().map(|conflict| format!("\n  {}", conflict)).collect()
+}
+
+fn violation_list(violations: &[Violation]) -> String {
+  violations.iter().map(|violation| format!("\n  {}", violation)).collect()
+}
+
+impl From<serde_json::Error> for AllocationError {
+  fn from(source: serde_json::Error) -> Self {
+    AllocationError::Json { line: source.line(), column: source.column(), source }
+  }
+}
@@ -0,0 +1,213 @@
+use codec::Decode;
+use sp_runtime::AccountId32;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::{parse_account, Allocation, AllocationError, Balance, TokenFormat};
+
+/// Encoding of an allocation file, detected from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+  /// `.json`: `{ "balances": [[account, balance], ...] }`.
+  Json,
+  /// `.csv`: `account,balance` rows, optionally preceded by a header row.
+  Csv,
+  /// `.scale` or `.bin`: a SCALE-encoded `Vec<(AccountId32, u128)>`, as written by the snapshot tool.
+  Scale,
+}
+
+impl InputFormat {
+  pub fn from_path(path: &Path) -> Result<Self, AllocationError> {
+    let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+      Some("json") => Ok(InputFormat::Json),
+      Some("csv") => Ok(InputFormat::Csv),
+      Some("scale") | Some("bin") => Ok(InputFormat::Scale),
+      _ => Err(AllocationError::UnknownFormat { path: path.to_path_buf() }),
+    }
+  }
+
+  /// Reads and parses every entry of an allocation in this format, reading JSON and CSV amounts
+  /// with `token`. SCALE input always holds planck values.
+  pub fn read(self, reader: impl Read, token: &TokenFormat) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+    match self {
+      InputFormat::Json => Allocation::from_reader(reader)?.parse(token),
+      InputFormat::Csv => parse_csv(reader, token),
+      InputFormat::Scale => decode_scale(reader),
+    }
+  }
+}
+
+/// Reads the allocation file at `path` in the format given by its extension.
+pub fn read_balances(
+  path: impl AsRef<Path>,
+  token: &TokenFormat,
+) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+  let path = path.as_ref();
+  let format = InputFormat::from_path(path)?;
+  let file = File::open(path)
+    .map_err(|source| AllocationError::Open { path: path.to_path_buf(), source })?;
+  format.read(file, token)
+}
+
+/// Parses CSV rows like [`Allocation::parse`], reporting invalid entries by their line in the file.
+fn parse_csv(reader: impl Read, token: &TokenFormat) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+  read_csv_rows(reader)?
+    .into_iter()
+    .map(|(line, account, balance)| {
+      let account_id = parse_account(&account)
+        .map_err(|reason| AllocationError::InvalidCsvAccount { line, account: account.clone(), reason })?;
+      let balance = token
+        .parse(&balance)
+        .map_err(|source| AllocationError::InvalidCsvBalance { line, balance: balance.clone(), source })?;
+      Ok((account_id, balance))
+    })
+    .collect()
+}
+
+/// The `(line, account, balance)` of every row but the header, lines counting from 1.
+///
+/// The first row is skipped as a header if neither its account nor its balance looks like one,
+/// so spreadsheets can be exported with or without column names.
+fn read_csv_rows(reader: impl Read) -> Result<Vec<(u64, String, String)>, AllocationError> {
+  let mut csv_reader = csv::ReaderBuilder::new()
+    .has_headers(false)
+    .flexible(true)
+    .trim(csv::Trim::All)
+    .from_reader(reader);
+  let mut balances = vec![];
+
+  for record in csv_reader.records() {
+    let record = record.map_err(|source| AllocationError::Csv {
+      line: source.position().map(|p| p.line()).unwrap_or_default(),
+      source,
+    })?;
+    let line = record.position().map(|p| p.line()).unwrap_or_default();
+    let (account, balance) = match (record.get(0), record.get(1), record.len()) {
+      (Some(account), Some(balance), 2) => (account, balance),
+      _ => return Err(AllocationError::CsvColumns { line, columns: record.len() }),
+    };
+    let is_header = balances.is_empty()
+      && line == 1
+      && parse_account(account).is_err()
+      && !balance.starts_with(|c: char| c.is_ascii_digit());
+    if !is_header {
+      balances.push((line, account.to_string(), balance.to_string()));
+    }
+  }
+
+  Ok(balances)
+}
+
+fn decode_scale(reader: impl Read) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+  let mut data = vec![];
+  BufReader::new(reader).read_to_end(&mut data).map_err(AllocationError::Read)?;
+  let input = &mut &data[..];
+  let balances = <Vec<(AccountId32, Balance)>>::decode(input)?;
+  if !input.is_empty() {
+    return Err(AllocationError::ScaleTrailingBytes(input.len()));
+  }
+  Ok(balances)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use codec::Encode;
+
+  fn account(byte: u8) -> AccountId32 {
+    AccountId32::from([byte; 32])
+  }
+
+  #[test]
+  fn detects_format_from_extension() {
+    assert_eq!(InputFormat::from_path(Path::new("a/balances.json")).unwrap(), InputFormat::Json);
+    assert_eq!(InputFormat::from_path(Path::new("Finance.CSV")).unwrap(), InputFormat::Csv);
+    assert_eq!(InputFormat::from_path(Path::new("snapshot.scale")).unwrap(), InputFormat::Scale);
+    assert_eq!(InputFormat::from_path(Path::new("snapshot.bin")).unwrap(), InputFormat::Scale);
+    assert!(InputFormat::from_path(Path::new("balances")).is_err());
+    assert!(InputFormat::from_path(Path::new("balances.xlsx")).is_err());
+  }
+
+  #[test]
+  fn reads_csv_with_header() {
+    let csv = "account,balance\n\
+      aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa, 20 planck\n\
+      \"0x0101010101010101010101010101010101010101010101010101010101010101\",10 planck\n";
+
+    assert_eq!(InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()).unwrap(), vec![(account(0xaa), 20), (account(1), 10)]);
+  }
+
+  #[test]
+  fn reads_csv_without_header() {
+    let csv = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,20 planck\n";
+
+    assert_eq!(InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()).unwrap(), vec![(account(0xaa), 20)]);
+  }
+
+  #[test]
+  fn reads_csv_token_amounts() {
+    let csv = "account,balance\n\
+      aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,\"1,000.5 DHX\"\n";
+
+    assert_eq!(
+      InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()).unwrap(),
+      vec![(account(0xaa), 1_000_500_000_000_000_000_000)]
+    );
+  }
+
+  #[test]
+  fn reports_csv_rows_with_wrong_column_count() {
+    let csv = "account,balance\nabc\n";
+
+    match InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()).unwrap_err() {
+      AllocationError::CsvColumns { line, columns } => assert_eq!((line, columns), (2, 1)),
+      err => panic!("unexpected error {:?}", err),
+    }
+  }
+
+  #[test]
+  fn invalid_first_row_with_numeric_balance_is_not_a_header() {
+    let csv = "not-an-account,20\n";
+
+    assert!(matches!(
+      InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()),
+      Err(AllocationError::InvalidCsvAccount { line: 1, .. })
+    ));
+  }
+
+  #[test]
+  fn reports_line_of_invalid_csv_entries() {
+    let csv = "account,balance\n\
+      aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,20 planck\n\
+      bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb,lots\n";
+
+    match InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()).unwrap_err() {
+      AllocationError::InvalidCsvBalance { line, balance, .. } => assert_eq!((line, balance.as_str()), (3, "lots")),
+      err => panic!("unexpected error {:?}", err),
+    }
+    let csv = "account,balance\nnot-an-account,20\n";
+    assert!(matches!(
+      InputFormat::Csv.read(csv.as_bytes(), &TokenFormat::default()),
+      Err(AllocationError::InvalidCsvAccount { line: 2, .. })
+    ));
+  }
+
+  #[test]
+  fn decodes_scale() {
+    let balances: Vec<(AccountId32, Balance)> = vec![(account(1), 1), (account(2), u128::MAX)];
+    let encoded = balances.encode();
+
+    assert_eq!(InputFormat::Scale.read(&encoded[..], &TokenFormat::default()).unwrap(), balances);
+  }
+
+  #[test]
+  fn rejects_truncated_or_padded_scale() {
+    let mut encoded = vec![(account(1), 1 as Balance)].encode();
+
+    assert!(matches!(InputFormat::Scale.read(&encoded[..encoded.len() - 1], &TokenFormat::default()), Err(AllocationError::Scale(_))));
+    encoded.push(0);
+    assert!(matches!(InputFormat::Scale.read(&encoded[..], &TokenFormat::default()), Err(AllocationError::ScaleTrailingBytes(1))));
+  }
+}
@@ -0,0 +1,183 @@
+use sp_core::crypto::{Pair, SecretStringError, Ss58AddressFormat, Ss58Codec};
+use sp_core::{ed25519, sr25519};
+use sp_runtime::AccountId32;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::AllocationError;
+
+/// Signature scheme of a generated or inspected keypair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scheme {
+  #[default]
+  Sr25519,
+  Ed25519,
+}
+
+impl Scheme {
+  pub const NAMES: [&'static str; 2] = ["sr25519", "ed25519"];
+}
+
+impl fmt::Display for Scheme {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Scheme::Sr25519 => Self::NAMES[0],
+      Scheme::Ed25519 => Self::NAMES[1],
+    };
+    f.write_str(name)
+  }
+}
+
+impl FromStr for Scheme {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "sr25519" => Ok(Scheme::Sr25519),
+      "ed25519" => Ok(Scheme::Ed25519),
+      _ => Err(format!("unknown scheme {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+    }
+  }
+}
+
+/// A keypair's public key together with the secrets it can be recreated from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyInfo {
+  pub scheme: Scheme,
+  pub public: AccountId32,
+  /// The mnemonic, only known for freshly generated keys.
+  pub phrase: Option<String>,
+  /// Mini secret key (sr25519) or secret key (ed25519), unknown after a soft derivation.
+  pub seed: Option<[u8; 32]>,
+}
+
+impl KeyInfo {
+  /// Generates a keypair from a new random 12 word mnemonic.
+  pub fn generate(scheme: Scheme) -> Self {
+    let (public, phrase, seed) = match scheme {
+      Scheme::Sr25519 => {
+        let (pair, phrase, seed) = sr25519::Pair::generate_with_phrase(None);
+        (pair.public().0, phrase, seed)
+      }
+      Scheme::Ed25519 => {
+        let (pair, phrase, seed) = ed25519::Pair::generate_with_phrase(None);
+        (pair.public().0, phrase, seed)
+      }
+    };
+    KeyInfo { scheme, public: public.into(), phrase: Some(phrase), seed: Some(seed) }
+  }
+
+  /// Recreates a keypair from a secret URI: a mnemonic or a `0x` hex seed, optionally followed by
+  /// `//hard` and `/soft` derivation junctions and `///password`. A URI that starts with a
+  /// junction, like `//Alice`, derives from the development mnemonic.
+  pub fn from_suri(scheme: Scheme, suri: &str, password: Option<&str>) -> Result<Self, AllocationError> {
+    let invalid = |e: SecretStringError| AllocationError::InvalidSecretUri(secret_error_reason(e));
+    let (public, seed) = match scheme {
+      Scheme::Sr25519 => {
+        let (pair, seed) = sr25519::Pair::from_string_with_seed(suri, password).map_err(invalid)?;
+        (pair.public().0, seed)
+      }
+      Scheme::Ed25519 => {
+        let (pair, seed) = ed25519::Pair::from_string_with_seed(suri, password).map_err(invalid)?;
+        (pair.public().0, seed)
+      }
+    };
+    Ok(KeyInfo { scheme, public: public.into(), phrase: None, seed })
+  }
+
+  pub fn address(&self, ss58_prefix: u16) -> String {
+    self.public.to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix))
+  }
+}
+
+fn secret_error_reason(error: SecretStringError) -> &'static str {
+  match error {
+    SecretStringError::InvalidFormat => "invalid format",
+    SecretStringError::InvalidPhrase => "not a valid BIP39 mnemonic",
+    SecretStringError::InvalidPassword => "invalid password",
+    SecretStringError::InvalidSeed => "invalid seed",
+    SecretStringError::InvalidSeedLength => "seed must be 32 bytes",
+    SecretStringError::InvalidPath => "invalid derivation path",
+  }
+}
+
+/// Parses a 32 byte public key given as hex, with or without `0x`.
+pub fn parse_public_key(public_key: &str) -> Result<AccountId32, AllocationError> {
+  let invalid = |reason| AllocationError::InvalidPublicKey { public_key: public_key.to_string(), reason };
+  let bytes = hex::decode(public_key.trim_start_matches("0x")).map_err(|_| invalid("not hex"))?;
+  let bytes: [u8; 32] = bytes.try_into().map_err(|_| invalid("must be 32 bytes"))?;
+  Ok(bytes.into())
+}
+
+/// Decodes an SS58 address into its public key and network prefix, verifying the checksum.
+pub fn decode_address(address: &str) -> Result<(AccountId32, u16), AllocationError> {
+  AccountId32::from_ss58check_with_version(address)
+    .map(|(public, format)| (public, u16::from(format)))
+    .map_err(|_| AllocationError::InvalidAddress(address.to_string()))
+}
+
+/// Whether `address` encodes `public_key`, whatever its network prefix.
+pub fn verify_address(address: &str, public_key: &str) -> Result<bool, AllocationError> {
+  Ok(decode_address(address)?.0 == parse_public_key(public_key)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use hex_literal::hex;
+
+  const ALICE_SR25519: [u8; 32] = hex!["d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"];
+  const ALICE_ED25519: [u8; 32] = hex!["88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee"];
+  const ALICE_ADDRESS: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+  #[test]
+  fn derives_development_keys() {
+    let sr25519 = KeyInfo::from_suri(Scheme::Sr25519, "//Alice", None).unwrap();
+    let ed25519 = KeyInfo::from_suri(Scheme::Ed25519, "//Alice", None).unwrap();
+
+    assert_eq!(sr25519.public, ALICE_SR25519.into());
+    assert_eq!(ed25519.public, ALICE_ED25519.into());
+    assert_eq!(sr25519.address(42), ALICE_ADDRESS);
+
+    let seed = format!("0x{}", hex::encode(sr25519.seed.unwrap()));
+    assert_eq!(KeyInfo::from_suri(Scheme::Sr25519, &seed, None).unwrap().public, ALICE_SR25519.into());
+    assert_eq!(KeyInfo::from_suri(Scheme::Sr25519, "//Alice/soft", None).unwrap().seed, None);
+  }
+
+  #[test]
+  fn recreates_generated_keys_from_phrase_and_seed() {
+    for scheme in [Scheme::Sr25519, Scheme::Ed25519] {
+      let generated = KeyInfo::generate(scheme);
+      let phrase = generated.phrase.clone().unwrap();
+      let seed = format!("0x{}", hex::encode(generated.seed.unwrap()));
+
+      assert_eq!(phrase.split_whitespace().count(), 12);
+      assert_eq!(KeyInfo::from_suri(scheme, &phrase, None).unwrap().public, generated.public);
+      assert_eq!(KeyInfo::from_suri(scheme, &seed, None).unwrap().public, generated.public);
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_secret_uri() {
+    let err = KeyInfo::from_suri(Scheme::Sr25519, "not a mnemonic at all", None).unwrap_err();
+
+    assert!(matches!(err, AllocationError::InvalidSecretUri(_)), "{:?}", err);
+  }
+
+  #[test]
+  fn converts_between_public_key_and_address() {
+    let (public, prefix) = decode_address(ALICE_ADDRESS).unwrap();
+    assert_eq!((public, prefix), (ALICE_SR25519.into(), 42));
+
+    let polkadot = KeyInfo::from_suri(Scheme::Sr25519, "//Alice", None).unwrap().address(0);
+    assert_eq!(decode_address(&polkadot).unwrap(), (ALICE_SR25519.into(), 0));
+  }
+
+  #[test]
+  fn verifies_address_against_public_key() {
+    assert!(verify_address(ALICE_ADDRESS, &hex::encode(ALICE_SR25519)).unwrap());
+    assert!(!verify_address(ALICE_ADDRESS, &format!("0x{}", hex::encode(ALICE_ED25519))).unwrap());
+    assert!(matches!(verify_address(ALICE_ADDRESS, "0x1234"), Err(AllocationError::InvalidPublicKey { .. })));
+    assert!(matches!(verify_address("5Grwva", &hex::encode(ALICE_SR25519)), Err(AllocationError::InvalidAddress(_))));
+  }
+}
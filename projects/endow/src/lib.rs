@@ -0,0 +1,245 @@
+//! Genesis allocation helpers for hardspooning a chain.
+//!
+//! An allocation file lists `[account, balance]` pairs, where the account is a hex public key or an
+//! SS58 address and the balance is a planck value or a token amount (see [`TokenFormat`]), e.g.
+//!
+//! ```json
+//! { "balances": [
+//!   ["a6b34be9aa95c82927b112dacf99bac1e728acb0fbae849097c0f9150fa49c23", "60,000 DHX"],
+//!   ["5FmxcuFwGK7kPmQCB3zhk3HtxxJUyb3WjxosF8jvnkrVRLUG", "8750500000000000000000 planck"]
+//! ] }
+//! ```
+//!
+//! The same pairs can also be given as CSV or SCALE-encoded, see [`InputFormat`]. JSON allocations
+//! may also endow multisig accounts under `multisigs` and derived accounts under `derived`, see
+//! [`MultisigEntry`] and [`DerivedEntry`], and list vesting schedules under `vesting`, see
+//! [`VestingEntry`].
+//!
+//! [`get_allocation`] merges those balances into a list of endowed accounts, e.g. the existing
+//! holders decoded from a `System.Account` state dump by [`read_snapshot`], resolving accounts
+//! present in both according to a [`MergeStrategy`]. The result is written out as the `balances`
+//! section of a chain spec by [`BalancesSection`], next to a [`VestingSection`] if there are vesting
+//! schedules.
+
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::Ss58Codec;
+use sp_runtime::{
+  traits::{
+      IdentifyAccount,
+      Verify,
+  },
+  AccountId32,
+  MultiSignature,
+};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+mod accounts;
+mod amount;
+mod chain_spec;
+mod checks;
+mod error;
+mod input;
+mod keys;
+mod merge;
+mod snapshot;
+mod vesting;
+
+pub use accounts::{derive_account, multi_account_id, DerivedEntry, MultisigEntry, MAX_SIGNATORIES};
+pub use amount::{AmountError, TokenFormat};
+pub use chain_spec::{read_spec_balances, BalancesSection, GenesisSections, SortOrder, SpecDiff};
+pub use checks::{check_duplicates, SanityChecks, Violation};
+pub use error::AllocationError;
+pub use input::{read_balances, InputFormat};
+pub use keys::{decode_address, parse_public_key, verify_address, KeyInfo, Scheme};
+pub use merge::{dedup_balances, merge_balances, Conflict, Dedup, DedupOutcome, Duplicate, MergeOutcome, MergeStrategy};
+pub use snapshot::{decode_account_pair, read_snapshot, system_account_prefix, Snapshot};
+pub use vesting::{
+  genesis_vesting, read_balances_with_vesting, BlockNumber, ParsedAllocation, Vesting, VestingEntry, VestingSection,
+};
+
+pub type Signature = MultiSignature;
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+pub type Balance = u128;
+
+/// Parsed entries of one section of an allocation file, e.g. its `multisigs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocationSection {
+  pub name: &'static str,
+  pub balances: Vec<(AccountId32, Balance)>,
+}
+
+/// Raw contents of an allocation file, before accounts and balances are parsed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Allocation {
+  pub balances: Vec<(String, String)>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub multisigs: Vec<MultisigEntry>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub derived: Vec<DerivedEntry>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub vesting: Vec<VestingEntry>,
+}
+
+impl Allocation {
+  /// Reads an allocation from JSON.
+  pub fn from_reader(reader: impl Read) -> Result<Self, AllocationError> {
+    let mut data = String::new();
+    BufReader::new(reader).read_to_string(&mut data).map_err(AllocationError::Read)?;
+    Ok(serde_json::from_str(&data)?)
+  }
+
+  /// Reads an allocation from the JSON file at `path`. Use [`read_balances`] to accept every
+  /// [`InputFormat`].
+  pub fn from_path(path: impl AsRef<Path>) -> Result<Self, AllocationError> {
+    let path = path.as_ref();
+    let file = File::open(path)
+      .map_err(|source| AllocationError::Open { path: path.to_path_buf(), source })?;
+    Self::from_reader(file)
+  }
+
+  /// Parses every entry into an account and a balance: the `balances` in file order, followed by
+  /// the `multisigs` and then the `derived` accounts.
+  pub fn parse(&self, token: &TokenFormat) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+    Ok(self.parse_sections(token)?.into_iter().flat_map(|section| section.balances).collect())
+  }
+
+  /// Parses the `balances`, `multisigs` and `derived` sections like [`Allocation::parse`], keeping
+  /// them apart so an entry can be found by its index within its section.
+  pub fn parse_sections(&self, token: &TokenFormat) -> Result<Vec<AllocationSection>, AllocationError> {
+    let balances = self
+      .balances
+      .iter()
+      .enumerate()
+      .map(|(index, (account, balance))| {
+        let account_id = parse_account(account)
+          .map_err(|reason| AllocationError::InvalidAccount { index, account: account.clone(), reason })?;
+        let balance = token
+          .parse(balance)
+          .map_err(|source| AllocationError::InvalidBalance { index, balance: balance.clone(), source })?;
+        Ok((account_id, balance))
+      })
+      .collect::<Result<_, AllocationError>>()?;
+    let multisigs =
+      self.multisigs.iter().enumerate().map(|(index, entry)| entry.parse(index, token)).collect::<Result<_, _>>()?;
+    let derived =
+      self.derived.iter().enumerate().map(|(index, entry)| entry.parse(index, token)).collect::<Result<_, _>>()?;
+    Ok(vec![
+      AllocationSection { name: "balances", balances },
+      AllocationSection { name: "multisigs", balances: multisigs },
+      AllocationSection { name: "derived", balances: derived },
+    ])
+  }
+}
+
+/// Parses a hex public key (with or without `0x`) or an SS58 address with any network prefix.
+pub fn parse_account(account: &str) -> Result<AccountId32, &'static str> {
+  if account.trim_start_matches("0x").len() == 64 {
+    AccountId32::from_str(account)
+  } else {
+    AccountId32::from_ss58check_with_version(account)
+      .map(|(account_id, _)| account_id)
+      .map_err(|_| "invalid ss58 address.")
+  }
+}
+
+/// Merges the balances of the allocation file at `path` into `endowed_accounts_with_balances`.
+///
+/// The file format is detected from its extension and amounts are read with `token`. Accounts that
+/// are only in the file are appended in file order, accounts present in both are resolved with
+/// `strategy` and listed in [`MergeOutcome::conflicts`].
+pub fn get_allocation(
+  endowed_accounts_with_balances: Vec<(AccountId32, Balance)>,
+  path: impl AsRef<Path>,
+  strategy: MergeStrategy,
+  token: &TokenFormat,
+) -> Result<MergeOutcome, AllocationError> {
+  merge_balances(endowed_accounts_with_balances, read_balances(path, token)?, strategy)
+}
+
+/// Same as [`get_allocation`], reading an allocation in `format` from `reader`.
+pub fn get_allocation_from_reader(
+  endowed_accounts_with_balances: Vec<(AccountId32, Balance)>,
+  reader: impl Read,
+  format: InputFormat,
+  strategy: MergeStrategy,
+  token: &TokenFormat,
+) -> Result<MergeOutcome, AllocationError> {
+  merge_balances(endowed_accounts_with_balances, format.read(reader, token)?, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use hex_literal::hex;
+
+  const ALICE: [u8; 32] = hex!["a6b34be9aa95c82927b112dacf99bac1e728acb0fbae849097c0f9150fa49c23"];
+
+  #[test]
+  fn merges_json_balances_into_endowed_accounts() {
+    let endowed = vec![(AccountId32::from(ALICE), 1), (AccountId32::from([1u8; 32]), 2)];
+    let json = r#"{ "balances": [
+      ["0xa6b34be9aa95c82927b112dacf99bac1e728acb0fbae849097c0f9150fa49c23", "10 planck"],
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "20 planck"]
+    ] }"#;
+
+    let combined = get_allocation_from_reader(endowed, json.as_bytes(), InputFormat::Json, MergeStrategy::Overwrite, &TokenFormat::default()).unwrap();
+    assert_eq!(combined.balances, vec![
+      (AccountId32::from(ALICE), 10),
+      (AccountId32::from([1u8; 32]), 2),
+      (AccountId32::from([0xaa; 32]), 20),
+    ]);
+  }
+
+  #[test]
+  fn loads_the_bundled_balances_file() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/balances.json");
+    assert_eq!(get_allocation(vec![], path, MergeStrategy::default(), &TokenFormat::default()).unwrap().balances.len(), 2);
+  }
+
+  #[test]
+  fn reports_missing_file() {
+    let err = get_allocation(vec![], "does/not/exist.json", MergeStrategy::default(), &TokenFormat::default()).unwrap_err();
+    assert!(matches!(err, AllocationError::Open { .. }), "{:?}", err);
+  }
+
+  #[test]
+  fn reports_line_of_bad_json() {
+    let json = "{\n  \"balances\": [\n    [\"aa\", 1]\n  ]\n}";
+    match Allocation::from_reader(json.as_bytes()).unwrap_err() {
+      AllocationError::Json { line, .. } => assert_eq!(line, 3),
+      err => panic!("unexpected error {:?}", err),
+    }
+  }
+
+  #[test]
+  fn reports_index_of_invalid_account() {
+    let json = r#"{ "balances": [
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "1 planck"],
+      ["5NotAnAddress", "2 planck"]
+    ] }"#;
+    match get_allocation_from_reader(vec![], json.as_bytes(), InputFormat::Json, MergeStrategy::default(), &TokenFormat::default()).unwrap_err() {
+      AllocationError::InvalidAccount { index, account, .. } => {
+        assert_eq!(index, 1);
+        assert_eq!(account, "5NotAnAddress");
+      }
+      err => panic!("unexpected error {:?}", err),
+    }
+  }
+
+  #[test]
+  fn reports_index_of_non_numeric_balance() {
+    let json = r#"{ "balances": [
+      ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY", "1_000"]
+    ] }"#;
+    match get_allocation_from_reader(vec![], json.as_bytes(), InputFormat::Json, MergeStrategy::default(), &TokenFormat::default()).unwrap_err() {
+      AllocationError::InvalidBalance { index, balance, .. } => {
+        assert_eq!(index, 0);
+        assert_eq!(balance, "1_000");
+      }
+      err => panic!("unexpected error {:?}", err),
+    }
+  }
+}
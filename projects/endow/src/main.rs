@@ -1,120 +1,368 @@
+use clap::{Parser, Subcommand};
+use endow::{
+  check_duplicates, decode_address, dedup_balances, genesis_vesting, merge_balances, parse_account, read_balances,
+  read_balances_with_vesting, read_snapshot, read_spec_balances, verify_address, AccountId, AllocationError, Balance,
+  BalancesSection, Dedup, Duplicate, GenesisSections, KeyInfo, MergeStrategy, SanityChecks, Scheme, SortOrder, SpecDiff,
+  TokenFormat, VestingSection,
+};
+use hex_literal::hex;
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
 
+const INITIAL_BALANCE: u128 = 8_750_000_000_000_000_000_000_u128;
+const INITIAL_DHX: u128 = 30_000_000_000_000_000_000_000_u128;
 
-use hex_literal::hex;
-use serde_json::{Result};
-use serde::{Deserialize, Serialize};
-use sp_runtime::{
-  traits::{
-      IdentifyAccount,
-      Verify,
+/// Writes the `balances` section of a chain spec from endowed accounts and an allocation file.
+///
+/// Every account appears once in the output. Accounts present in both inputs are resolved with
+/// `--merge-strategy`, accounts repeated within one input are collapsed with `--dedup`, and both
+/// are listed on stderr. If the allocation has vesting schedules, the output
+/// holds both the `balances` and the `vesting` section.
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+  #[command(subcommand)]
+  command: Option<Command>,
+  /// Allocation file (.json, .csv, or SCALE-encoded .scale/.bin), defaults to the example bundled
+  /// with the crate.
+  #[arg(default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/src/balances.json"))]
+  balances: PathBuf,
+  /// Allocation file with the endowed accounts, in any format accepted for the allocation.
+  /// Defaults to the built-in endowed accounts.
+  #[arg(long)]
+  endowed: Option<PathBuf>,
+  /// `state_getPairs` dump of the `System.Account` storage of the chain being hardspooned, used as
+  /// the endowed accounts.
+  #[arg(long, conflicts_with = "endowed")]
+  snapshot: Option<PathBuf>,
+  /// Leave out snapshot accounts whose free plus reserved balance is below this amount.
+  #[arg(long, default_value = "0 planck", requires = "snapshot")]
+  min_balance: String,
+  /// How to resolve accounts that are both endowed and in the allocation:
+  /// overwrite, keep-endowed, sum or error-on-conflict.
+  #[arg(long, default_value_t = MergeStrategy::Overwrite)]
+  merge_strategy: MergeStrategy,
+  /// Collapse accounts listed more than once within the endowed accounts or the allocation,
+  /// keeping the first or the last balance or their sum. Without it repeated accounts are an error.
+  #[arg(long)]
+  dedup: Option<Dedup>,
+  /// Minimum balance of every account, the runtime's `ExistentialDeposit`, as a token amount like
+  /// "0.001 DHX" or in planck like "500 planck".
+  #[arg(long, default_value = "0 planck")]
+  existential_deposit: String,
+  /// Fail unless the balances add up to this total issuance, as a token amount like "138,750 DHX"
+  /// or in planck.
+  #[arg(long)]
+  expected_total: Option<String>,
+  /// Decimals of the native token, used for token amounts in the input and the printed totals.
+  #[arg(long, default_value_t = 18)]
+  decimals: u8,
+  /// Symbol of the native token.
+  #[arg(long, default_value = "DHX")]
+  symbol: String,
+  /// Read amounts without a unit as planck, for allocation files written before token amounts
+  /// were supported. Without this flag every amount needs the token symbol or a "planck" suffix.
+  #[arg(long)]
+  bare_planck: bool,
+  /// SS58 network prefix of the addresses in the output, e.g. 0 for Polkadot or 42 for Substrate.
+  #[arg(long, default_value_t = 42)]
+  ss58_prefix: u16,
+  /// Order of the output: input, account or balance.
+  #[arg(long, default_value_t = SortOrder::Input)]
+  sort: SortOrder,
+  /// File to write the section to instead of stdout.
+  #[arg(long, short, conflicts_with = "check")]
+  output: Option<PathBuf>,
+  /// Compare with the balances in an existing chain spec instead of writing them, exiting with 1
+  /// if they differ. Only the `balances` section is compared, a `vesting` section in the spec is
+  /// not checked.
+  #[arg(long)]
+  check: Option<PathBuf>,
+}
+
+/// Key and address utilities for preparing allocations.
+#[derive(Debug, Subcommand)]
+enum Command {
+  /// Generate a keypair from a new random mnemonic.
+  Generate {
+    #[arg(long, default_value_t = Scheme::Sr25519)]
+    scheme: Scheme,
+    #[arg(long, default_value_t = 42)]
+    ss58_prefix: u16,
   },
-	AccountId32,
-  MultiSignature,
-};
-use sp_core::{
-  crypto::{
-      UncheckedFrom,
+  /// Show the public key and address of a mnemonic, a 0x seed or a secret URI like "//Alice".
+  Inspect {
+    suri: String,
+    #[arg(long, default_value_t = Scheme::Sr25519)]
+    scheme: Scheme,
+    /// Password of the mnemonic, overriding any `///password` in the URI.
+    #[arg(long)]
+    password: Option<String>,
+    #[arg(long, default_value_t = 42)]
+    ss58_prefix: u16,
   },
-};
-use std::fs::File;
-use std::io::Read;
-use std::str::FromStr;
-
-#[derive(Serialize, Deserialize)]
-struct Allocation {
-    balances: Vec<(String, String)>
+  /// Convert a hex public key or an SS58 address to the public key and the address with
+  /// --ss58-prefix.
+  Address {
+    account: String,
+    #[arg(long, default_value_t = 42)]
+    ss58_prefix: u16,
+  },
+  /// Check that an SS58 address belongs to a hex public key, exiting with 1 if it does not.
+  Verify { address: String, public_key: String },
 }
 
-type Signature = MultiSignature;
-type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
-type Balance = u128;
+fn default_endowed_accounts() -> Vec<(AccountId, Balance)> {
+  vec![
+    (hex!["a42b7518d62a942344fec55d414f1654bf3fd325dbfa32a3c30534d5976acb21"].into(), INITIAL_DHX),
+    (hex!["106c208ac262aa3733629ad0860d0dc72d8b9152e1cdcab497949a3f9504517a"].into(), INITIAL_BALANCE),
+  ]
+}
 
-enum Error { Invalid }
+fn print_key(key: &KeyInfo, ss58_prefix: u16) {
+  if let Some(phrase) = &key.phrase {
+    println!("Secret phrase:     {}", phrase);
+  }
+  if let Some(seed) = &key.seed {
+    println!("Secret seed:       0x{}", hex::encode(seed));
+  }
+  println!("Scheme:            {}", key.scheme);
+  println!("Public key (hex):  0x{}", hex::encode(&key.public));
+  println!("SS58 address:      {}", key.address(ss58_prefix));
+}
 
-fn get_allocation(
-  endowed_accounts_with_balances: Vec<(AccountId32, Balance)>
-) -> Result<Vec<(AccountId32, Balance)>> {
-  let mut file = File::open("/Users/ls2/code/github/ltfschoen/RustTest/projects/endow/src/balances.json").unwrap();
-  let mut data = String::new();
-  file.read_to_string(&mut data).unwrap();
+fn run_command(command: Command) -> Result<bool, AllocationError> {
+  match command {
+    Command::Generate { scheme, ss58_prefix } => print_key(&KeyInfo::generate(scheme), ss58_prefix),
+    Command::Inspect { suri, scheme, password, ss58_prefix } => {
+      print_key(&KeyInfo::from_suri(scheme, &suri, password.as_deref())?, ss58_prefix)
+    }
+    Command::Address { account, ss58_prefix } => {
+      let public = match decode_address(&account) {
+        Ok((public, prefix)) => {
+          println!("SS58 prefix:       {}", prefix);
+          public
+        }
+        Err(_) => parse_account(&account)
+          .map_err(|reason| AllocationError::InvalidPublicKey { public_key: account.clone(), reason })?,
+      };
+      println!("Public key (hex):  0x{}", hex::encode(&public));
+      println!("SS58 address:      {}", public.to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)));
+    }
+    Command::Verify { address, public_key } => {
+      if !verify_address(&address, &public_key)? {
+        eprintln!("{} does not belong to {}", address, public_key);
+        return Ok(false);
+      }
+      eprintln!("{} belongs to {}", address, public_key);
+    }
+  }
+  Ok(true)
+}
 
-  let json: Allocation = serde_json::from_str(&data)?;
-  let balances_json = json.balances;
-  println!("balances_json {:#?}", balances_json.clone());
+fn parse_amount(token: &TokenFormat, option: &'static str, amount: &str) -> Result<Balance, AllocationError> {
+  token
+    .parse(amount)
+    .map_err(|source| AllocationError::InvalidAmount { option, amount: amount.to_string(), source })
+}
 
-  let mut combined_balances: Vec<(AccountId32, Balance)> = vec![];
+/// Collapses repeated accounts of `balances` with `dedup`, if given, appending them to `duplicates`.
+fn dedup(
+  balances: Vec<(AccountId, Balance)>,
+  dedup: Option<Dedup>,
+  duplicates: &mut Vec<Duplicate>,
+) -> Result<Vec<(AccountId, Balance)>, AllocationError> {
+  match dedup {
+    Some(dedup) => {
+      let outcome = dedup_balances(balances, dedup)?;
+      duplicates.extend(outcome.duplicates);
+      Ok(outcome.balances)
+    }
+    None => Ok(balances),
+  }
+}
 
-  if endowed_accounts_with_balances.len() != 0 {
-    for e in endowed_accounts_with_balances {
-      let account_public_key_endowed: String = e.0.to_string();
-      println!("account_public_key_endowed {:#?}", account_public_key_endowed.clone());
+fn run(cli: Cli) -> Result<bool, AllocationError> {
+  if let Some(command) = cli.command {
+    return run_command(command);
+  }
+  let token = TokenFormat { decimals: cli.decimals, symbol: cli.symbol.clone(), bare_planck: cli.bare_planck };
+  let existential_deposit = parse_amount(&token, "existential-deposit", &cli.existential_deposit)?;
+  let expected_total =
+    cli.expected_total.as_deref().map(|total| parse_amount(&token, "expected-total", total)).transpose()?;
 
-      let account_balance_endowed: Balance = e.1.to_string().parse::<Balance>().unwrap();
-      println!("account_balance_endowed {:#?}", account_balance_endowed.clone());
+  let min_balance = parse_amount(&token, "min-balance", &cli.min_balance)?;
 
-      let account_ss58_address_endowed: AccountId32 = AccountId32::from_str(&account_public_key_endowed).unwrap();
-      println!("account_ss58_address_endowed {:#?}", account_ss58_address_endowed.clone());
+  let endowed_accounts_with_balances = match (&cli.endowed, &cli.snapshot) {
+    (Some(path), _) => read_balances(path, &token)?,
+    (None, Some(path)) => read_snapshot(path, min_balance)?,
+    (None, None) => default_endowed_accounts(),
+  };
+  let allocation = read_balances_with_vesting(&cli.balances, &token)?;
+  // The merge keeps one balance per account, so repeated rows only show in the raw entries. Every
+  // other check waits for the merged balances, which e.g. --merge-strategy sum can lift above the
+  // existential deposit.
+  if cli.dedup.is_none() {
+    check_duplicates(&allocation.sections)?;
+  }
+  let mut duplicates = vec![];
+  let endowed_accounts_with_balances = dedup(endowed_accounts_with_balances, cli.dedup, &mut duplicates)?;
+  let allocated = dedup(allocation.balances(), cli.dedup, &mut duplicates)?;
+  let vesting = allocation.vesting;
+  let checks = SanityChecks { existential_deposit, expected_total };
+  let outcome = merge_balances(endowed_accounts_with_balances, allocated, cli.merge_strategy)?;
 
-      combined_balances.push((account_ss58_address_endowed.clone(), account_balance_endowed.clone()));
+  // Conflict report for audits: every account collapsed by --dedup and every account whose endowed
+  // balance met an allocated one.
+  if let Some(dedup) = cli.dedup {
+    eprintln!("{} repeated accounts collapsed with {}", duplicates.len(), dedup);
+    for duplicate in &duplicates {
+      eprintln!("  {}", duplicate);
     }
   }
+  eprintln!("{} conflicts resolved with {}", outcome.conflicts.len(), cli.merge_strategy);
+  for conflict in &outcome.conflicts {
+    eprintln!("  {}", conflict);
+  }
 
-  if balances_json.len() != 0 {
-    for e in balances_json {
-      let account_public_key_json: String = e.0.to_string();
-      println!("account_public_key_json {:#?}", account_public_key_json.clone());
+  let total_issuance = checks.check(&outcome.balances)?;
+  eprintln!(
+    "{} accounts, total issuance {} ({} planck)",
+    outcome.balances.len(),
+    token.format(total_issuance),
+    total_issuance
+  );
+  let genesis_vesting = genesis_vesting(&vesting, &outcome.balances)?;
+  if !vesting.is_empty() {
+    let locked = vesting.iter().map(|schedule| schedule.locked).sum();
+    eprintln!("{} vesting schedules, {} locked", vesting.len(), token.format(locked));
+  }
 
-      let account_balance_json: Balance = e.1.to_string().parse::<Balance>().unwrap();
-      println!("account_balance_json {:#?}", account_balance_json.clone());
+  let mut balances = outcome.balances;
+  cli.sort.sort(&mut balances);
 
-      let account_ss58_address_json: AccountId32 = AccountId32::from_str(&account_public_key_json).unwrap();
-      println!("account_ss58_address_json {:#?}", account_ss58_address_json.clone());
+  if let Some(spec) = &cli.check {
+    let diff = SpecDiff::new(&read_spec_balances(spec)?.parse()?, &balances);
+    if diff.is_empty() {
+      eprintln!("{} matches the generated balances", spec.display());
+      return Ok(true);
+    }
+    eprint!("{} differs from the generated balances:\n{}", spec.display(), diff);
+    return Ok(false);
+  }
 
-      let index_of_match = combined_balances.clone().iter().position(|x| x.0 == account_ss58_address_json.clone());
+  let section = BalancesSection::new(&balances, cli.ss58_prefix);
+  let json = if genesis_vesting.is_empty() {
+    serde_json::to_string_pretty(&section)?
+  } else {
+    let vesting = VestingSection::new(&genesis_vesting, cli.ss58_prefix);
+    serde_json::to_string_pretty(&GenesisSections { balances: section, vesting })?
+  };
+  match &cli.output {
+    Some(path) => fs::write(path, json + "\n")
+      .map_err(|source| AllocationError::Write { path: path.clone(), source })?,
+    None => println!("{}", json),
+  }
+  Ok(true)
+}
 
-      if let Some(idx) = index_of_match.clone() {
-          println!("match b/w endowed and json for {:#?} so overwriting its balance value", account_ss58_address_json.clone());
-          combined_balances[idx] = (combined_balances[idx].0.clone(), account_balance_json.clone());
-      } else {
-          println!("no match b/w endowed and json for {:#?} so adding it to the list", e.0.clone());
-          combined_balances.push((account_ss58_address_json.clone(), account_balance_json.clone()));
-      }
+fn main() {
+  match run(Cli::parse()) {
+    Ok(true) => (),
+    Ok(false) => process::exit(1),
+    Err(e) => {
+      eprintln!("error: {}", e);
+      process::exit(2);
     }
   }
-
-  return Ok(combined_balances);
 }
 
-const INITIAL_BALANCE: u128 = 8_750_000_000_000_000_000_000_u128;
-const INITIAL_DHX: u128 = 30_000_000_000_000_000_000_000_u128;
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use endow::Violation;
 
-fn main() {
-  let mut endowed_accounts_with_balances: Vec<(AccountId, Balance)> = vec![];
-  let endowed_accounts: Vec<AccountId> =
-    vec![
-      hex!["a42b7518d62a942344fec55d414f1654bf3fd325dbfa32a3c30534d5976acb21"].into(),
-      hex!["106c208ac262aa3733629ad0860d0dc72d8b9152e1cdcab497949a3f9504517a"].into(),
-    ];
-  for x in endowed_accounts {
-      if x == UncheckedFrom::unchecked_from(
-          hex!("a42b7518d62a942344fec55d414f1654bf3fd325dbfa32a3c30534d5976acb21").into(),
-      ) {
-          endowed_accounts_with_balances.push((x, INITIAL_DHX));
-      } else {
-          println!("endowed_accounts_with_balances INITIAL_BALANCE {:?}", x.clone());
-          endowed_accounts_with_balances.push((x, INITIAL_BALANCE));
+  #[test]
+  fn reports_duplicated_allocation_rows() {
+    let dir = tempfile::tempdir().unwrap();
+    let balances = dir.path().join("balances.json");
+    fs::write(&balances, r#"{ "balances": [
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "1 DHX"],
+      ["bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "2 DHX"],
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "1 DHX"]
+    ] }"#)
+    .unwrap();
+    let output = dir.path().join("spec.json");
+    let cli = Cli::parse_from(["endow", balances.to_str().unwrap(), "--output", output.to_str().unwrap()]);
+
+    match run(cli).unwrap_err() {
+      AllocationError::Sanity(violations) => assert_eq!(violations, vec![Violation::DuplicateAccount {
+        account: AccountId::from([0xaa; 32]),
+        entries: vec![("balances", 0), ("balances", 2)],
+      }]),
+      err => panic!("unexpected error {:?}", err),
+    }
+    assert!(!output.exists());
+  }
+
+  #[test]
+  fn checks_the_existential_deposit_after_merging() {
+    let dir = tempfile::tempdir().unwrap();
+    let endowed = dir.path().join("endowed.json");
+    fs::write(&endowed, r#"{ "balances": [
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "600 planck"]
+    ] }"#)
+    .unwrap();
+    let balances = dir.path().join("balances.json");
+    fs::write(&balances, r#"{ "balances": [
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "400 planck"]
+    ] }"#)
+    .unwrap();
+    let output = dir.path().join("spec.json");
+    let args = |strategy| {
+      Cli::parse_from([
+        "endow",
+        balances.to_str().unwrap(),
+        "--endowed",
+        endowed.to_str().unwrap(),
+        "--merge-strategy",
+        strategy,
+        "--existential-deposit",
+        "500 planck",
+        "--output",
+        output.to_str().unwrap(),
+      ])
+    };
+
+    assert!(run(args("sum")).unwrap());
+    match run(args("overwrite")).unwrap_err() {
+      AllocationError::Sanity(violations) => {
+        assert!(matches!(violations[..], [Violation::BelowExistentialDeposit { balance: 400, .. }]), "{:?}", violations)
       }
+      err => panic!("unexpected error {:?}", err),
+    }
   }
 
-  let hardspoon_balances = get_allocation(endowed_accounts_with_balances.clone()).unwrap();
-  println!("hardspoon_balances {:#?}", hardspoon_balances.clone());
-
-  let output = hardspoon_balances
-    .iter()
-    .cloned()
-    .map(|x| {
-        return (x.0.clone(), x.1.clone());
-    })
-    .collect::<Vec<(AccountId, Balance)>>();
-    println!("output {:#?}", output.clone());
+  #[test]
+  fn dedup_collapses_allocation_rows() {
+    let dir = tempfile::tempdir().unwrap();
+    let balances = dir.path().join("balances.json");
+    fs::write(&balances, r#"{ "balances": [
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "1 DHX"],
+      ["bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "2 DHX"],
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "3 DHX"]
+    ] }"#)
+    .unwrap();
+    let output = dir.path().join("spec.json");
+    let cli =
+      Cli::parse_from(["endow", balances.to_str().unwrap(), "--dedup", "sum", "--output", output.to_str().unwrap()]);
+
+    assert!(run(cli).unwrap());
+    let written = read_spec_balances(&output).unwrap().parse().unwrap();
+    let dhx = 1_000_000_000_000_000_000;
+    assert!(written.contains(&(AccountId::from([0xaa; 32]), 4 * dhx)), "{:?}", written);
+    assert!(written.contains(&(AccountId::from([0xbb; 32]), 2 * dhx)), "{:?}", written);
+  }
 }
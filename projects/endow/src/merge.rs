@@ -0,0 +1,358 @@
+use indexmap::{map::Entry, IndexMap};
+use sp_runtime::AccountId32;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{AllocationError, Balance};
+
+/// How to resolve an allocation balance for an account that already has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+  /// The allocation balance replaces the endowed one.
+  #[default]
+  Overwrite,
+  /// The endowed balance is kept and the allocation balance ignored.
+  KeepEndowed,
+  /// Both balances are added, failing if the sum overflows a `Balance`.
+  Sum,
+  /// Any collision is an error listing every conflicting account.
+  ErrorOnConflict,
+}
+
+impl MergeStrategy {
+  pub const NAMES: [&'static str; 4] = ["overwrite", "keep-endowed", "sum", "error-on-conflict"];
+}
+
+impl fmt::Display for MergeStrategy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      MergeStrategy::Overwrite => Self::NAMES[0],
+      MergeStrategy::KeepEndowed => Self::NAMES[1],
+      MergeStrategy::Sum => Self::NAMES[2],
+      MergeStrategy::ErrorOnConflict => Self::NAMES[3],
+    };
+    f.write_str(name)
+  }
+}
+
+impl FromStr for MergeStrategy {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "overwrite" => Ok(MergeStrategy::Overwrite),
+      "keep-endowed" => Ok(MergeStrategy::KeepEndowed),
+      "sum" => Ok(MergeStrategy::Sum),
+      "error-on-conflict" => Ok(MergeStrategy::ErrorOnConflict),
+      _ => Err(format!("unknown merge strategy {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+    }
+  }
+}
+
+/// How to collapse rows that repeat an account within one input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dedup {
+  /// The first row's balance is kept.
+  First,
+  /// The last row's balance is kept.
+  Last,
+  /// The balances of all rows are added, failing if the sum overflows a `Balance`.
+  Sum,
+}
+
+impl Dedup {
+  pub const NAMES: [&'static str; 3] = ["first", "last", "sum"];
+}
+
+impl fmt::Display for Dedup {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Dedup::First => Self::NAMES[0],
+      Dedup::Last => Self::NAMES[1],
+      Dedup::Sum => Self::NAMES[2],
+    };
+    f.write_str(name)
+  }
+}
+
+impl FromStr for Dedup {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "first" => Ok(Dedup::First),
+      "last" => Ok(Dedup::Last),
+      "sum" => Ok(Dedup::Sum),
+      _ => Err(format!("unknown dedup mode {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+    }
+  }
+}
+
+/// An account listed more than once within one input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+  pub account: AccountId32,
+  /// Balance of every row, in input order.
+  pub balances: Vec<Balance>,
+  pub kept: Balance,
+}
+
+impl fmt::Display for Duplicate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: rows {:?}, kept {}", self.account, self.balances, self.kept)
+  }
+}
+
+/// Balances with one row per account together with every account that was collapsed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DedupOutcome {
+  pub balances: Vec<(AccountId32, Balance)>,
+  pub duplicates: Vec<Duplicate>,
+}
+
+/// Collapses rows of `balances` that repeat an account into one row, according to `dedup`.
+///
+/// Each account stays at the position of its first row.
+pub fn dedup_balances(
+  balances: impl IntoIterator<Item = (AccountId32, Balance)>,
+  dedup: Dedup,
+) -> Result<DedupOutcome, AllocationError> {
+  let mut rows: IndexMap<AccountId32, Vec<Balance>> = IndexMap::new();
+  for (account, balance) in balances {
+    rows.entry(account).or_default().push(balance);
+  }
+
+  let mut duplicates = vec![];
+  let mut collapsed = Vec::with_capacity(rows.len());
+  for (account, balances) in rows {
+    let kept = match dedup {
+      Dedup::First => balances[0],
+      Dedup::Last => balances[balances.len() - 1],
+      Dedup::Sum => balances
+        .iter()
+        .try_fold(0 as Balance, |sum, balance| sum.checked_add(*balance))
+        .ok_or_else(|| AllocationError::DuplicateOverflow { account: account.clone() })?,
+    };
+    if balances.len() > 1 {
+      duplicates.push(Duplicate { account: account.clone(), balances, kept });
+    }
+    collapsed.push((account, kept));
+  }
+
+  Ok(DedupOutcome { balances: collapsed, duplicates })
+}
+
+/// An account that is both endowed and in the allocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+  pub account: AccountId32,
+  pub endowed: Balance,
+  pub allocated: Balance,
+  pub merged: Balance,
+}
+
+impl fmt::Display for Conflict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: endowed {}, allocated {}, merged {}", self.account, self.endowed, self.allocated, self.merged)
+  }
+}
+
+/// Merged balances together with every conflict that was resolved along the way.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergeOutcome {
+  pub balances: Vec<(AccountId32, Balance)>,
+  pub conflicts: Vec<Conflict>,
+}
+
+/// Merges `balances` into `endowed_accounts_with_balances`, resolving collisions with `strategy`.
+///
+/// Accounts keep the order in which they were first seen, new accounts are appended. Each account
+/// is looked up in a hash index, which keeps the merge linear in the number of accounts.
+///
+/// The strategy only applies between the two inputs. An account listed twice within the endowed
+/// accounts or within the allocation is an [`AllocationError::DuplicateAccounts`] instead, unless
+/// the input was collapsed with [`dedup_balances`] first.
+pub fn merge_balances(
+  endowed_accounts_with_balances: Vec<(AccountId32, Balance)>,
+  balances: impl IntoIterator<Item = (AccountId32, Balance)>,
+  strategy: MergeStrategy,
+) -> Result<MergeOutcome, AllocationError> {
+  let mut combined_balances: IndexMap<AccountId32, Balance> = IndexMap::new();
+  let mut duplicates = vec![];
+  for (account, endowed) in endowed_accounts_with_balances {
+    if let Entry::Occupied(_) = combined_balances.entry(account.clone()) {
+      push_once(&mut duplicates, account);
+    } else {
+      combined_balances.insert(account, endowed);
+    }
+  }
+  if !duplicates.is_empty() {
+    return Err(AllocationError::DuplicateAccounts { input: "endowed accounts", accounts: duplicates });
+  }
+
+  let mut allocated_accounts = HashSet::new();
+  let mut conflicts = vec![];
+
+  for (account, allocated) in balances {
+    if !allocated_accounts.insert(account.clone()) {
+      push_once(&mut duplicates, account);
+      continue;
+    }
+    match combined_balances.entry(account) {
+      Entry::Vacant(entry) => {
+        entry.insert(allocated);
+      }
+      Entry::Occupied(mut entry) => {
+        let endowed = *entry.get();
+        let merged = match strategy {
+          MergeStrategy::Overwrite => allocated,
+          MergeStrategy::KeepEndowed | MergeStrategy::ErrorOnConflict => endowed,
+          MergeStrategy::Sum => endowed.checked_add(allocated).ok_or_else(|| {
+            AllocationError::BalanceOverflow { account: entry.key().clone(), endowed, allocated }
+          })?,
+        };
+        entry.insert(merged);
+        conflicts.push(Conflict { account: entry.key().clone(), endowed, allocated, merged });
+      }
+    }
+  }
+
+  if !duplicates.is_empty() {
+    return Err(AllocationError::DuplicateAccounts { input: "allocation", accounts: duplicates });
+  }
+  if strategy == MergeStrategy::ErrorOnConflict && !conflicts.is_empty() {
+    return Err(AllocationError::Conflicts(conflicts));
+  }
+
+  Ok(MergeOutcome { balances: combined_balances.into_iter().collect(), conflicts })
+}
+
+fn push_once(accounts: &mut Vec<AccountId32>, account: AccountId32) {
+  if !accounts.contains(&account) {
+    accounts.push(account);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn account(byte: u8) -> AccountId32 {
+    AccountId32::from([byte; 32])
+  }
+
+  fn merge(strategy: MergeStrategy) -> Result<MergeOutcome, AllocationError> {
+    let endowed = vec![(account(3), 30), (account(1), 10)];
+    let balances = vec![(account(2), 20), (account(1), 11), (account(4), 40)];
+    merge_balances(endowed, balances, strategy)
+  }
+
+  #[test]
+  fn keeps_first_seen_order() {
+    assert_eq!(merge(MergeStrategy::Overwrite).unwrap().balances, vec![
+      (account(3), 30),
+      (account(1), 11),
+      (account(2), 20),
+      (account(4), 40),
+    ]);
+  }
+
+  #[test]
+  fn reports_duplicates_within_an_input_apart_from_conflicts() {
+    let balances = vec![(account(1), 1), (account(2), 2), (account(1), 3), (account(2), 4), (account(1), 5)];
+
+    for strategy in [MergeStrategy::Overwrite, MergeStrategy::KeepEndowed, MergeStrategy::Sum] {
+      match merge_balances(vec![(account(2), 20)], balances.clone(), strategy).unwrap_err() {
+        AllocationError::DuplicateAccounts { input, accounts } => {
+          assert_eq!(input, "allocation");
+          assert_eq!(accounts, vec![account(1), account(2)]);
+        }
+        err => panic!("unexpected error {:?}", err),
+      }
+    }
+    match merge_balances(vec![(account(3), 1), (account(3), 1)], vec![], MergeStrategy::Overwrite).unwrap_err() {
+      AllocationError::DuplicateAccounts { input, accounts } => assert_eq!((input, accounts), ("endowed accounts", vec![account(3)])),
+      err => panic!("unexpected error {:?}", err),
+    }
+  }
+
+  #[test]
+  fn reports_every_conflict() {
+    let outcome = merge(MergeStrategy::Overwrite).unwrap();
+
+    assert_eq!(outcome.conflicts, vec![Conflict { account: account(1), endowed: 10, allocated: 11, merged: 11 }]);
+  }
+
+  #[test]
+  fn keep_endowed_ignores_allocated_balance() {
+    let outcome = merge(MergeStrategy::KeepEndowed).unwrap();
+
+    assert_eq!(outcome.balances[1], (account(1), 10));
+    assert_eq!(outcome.conflicts[0].merged, 10);
+  }
+
+  #[test]
+  fn sum_adds_both_balances() {
+    let outcome = merge(MergeStrategy::Sum).unwrap();
+
+    assert_eq!(outcome.balances[1], (account(1), 21));
+  }
+
+  #[test]
+  fn sum_fails_on_overflow() {
+    let endowed = vec![(account(1), Balance::MAX)];
+    let err = merge_balances(endowed, vec![(account(1), 1)], MergeStrategy::Sum).unwrap_err();
+
+    assert!(matches!(err, AllocationError::BalanceOverflow { endowed: Balance::MAX, allocated: 1, .. }), "{:?}", err);
+  }
+
+  #[test]
+  fn error_on_conflict_lists_all_conflicts() {
+    let endowed = vec![(account(1), 1), (account(2), 2)];
+    let balances = vec![(account(1), 10), (account(3), 30), (account(2), 20)];
+
+    match merge_balances(endowed, balances, MergeStrategy::ErrorOnConflict).unwrap_err() {
+      AllocationError::Conflicts(conflicts) => {
+        let accounts: Vec<_> = conflicts.into_iter().map(|c| c.account).collect();
+        assert_eq!(accounts, vec![account(1), account(2)]);
+      }
+      err => panic!("unexpected error {:?}", err),
+    }
+  }
+
+  #[test]
+  fn dedup_collapses_repeated_accounts() {
+    let balances = vec![(account(1), 1), (account(2), 2), (account(1), 3), (account(3), 4), (account(1), 5)];
+
+    for (dedup, kept) in [(Dedup::First, 1), (Dedup::Last, 5), (Dedup::Sum, 9)] {
+      assert_eq!(dedup_balances(balances.clone(), dedup).unwrap(), DedupOutcome {
+        balances: vec![(account(1), kept), (account(2), 2), (account(3), 4)],
+        duplicates: vec![Duplicate { account: account(1), balances: vec![1, 3, 5], kept }],
+      });
+    }
+  }
+
+  #[test]
+  fn dedup_sum_fails_on_overflow() {
+    let err = dedup_balances(vec![(account(1), Balance::MAX), (account(1), 1)], Dedup::Sum).unwrap_err();
+
+    assert!(matches!(err, AllocationError::DuplicateOverflow { .. }), "{:?}", err);
+  }
+
+  #[test]
+  fn dedup_names_round_trip() {
+    for name in Dedup::NAMES {
+      assert_eq!(name.parse::<Dedup>().unwrap().to_string(), name);
+    }
+    assert!("keep".parse::<Dedup>().is_err());
+  }
+
+  #[test]
+  fn strategy_names_round_trip() {
+    for name in MergeStrategy::NAMES {
+      assert_eq!(name.parse::<MergeStrategy>().unwrap().to_string(), name);
+    }
+    assert!("replace".parse::<MergeStrategy>().is_err());
+  }
+}
@@ -0,0 +1,206 @@
+use codec::Decode;
+use serde::Deserialize;
+use sp_core::hashing::{blake2_128, twox_128};
+use sp_runtime::AccountId32;
+use std::fs;
+use std::path::Path;
+
+use crate::{AllocationError, Balance};
+
+/// `AccountData` of `pallet_balances`. Only `free` and `reserved` count towards the balance.
+#[derive(Decode)]
+struct AccountData {
+  free: Balance,
+  reserved: Balance,
+  _misc_frozen: Balance,
+  _fee_frozen: Balance,
+}
+
+/// `AccountInfo` of `frame_system` since reference counts were split into consumers, providers
+/// and sufficients.
+#[derive(Decode)]
+struct AccountInfo {
+  _nonce: u32,
+  _consumers: u32,
+  _providers: u32,
+  _sufficients: u32,
+  data: AccountData,
+}
+
+/// `AccountInfo` of chains that predate the `sufficients` counter.
+#[derive(Decode)]
+struct LegacyAccountInfo {
+  _nonce: u32,
+  _consumers: u32,
+  _providers: u32,
+  data: AccountData,
+}
+
+/// A `state_getPairs` result, either bare or as the full JSON-RPC response.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Pairs {
+  Response { result: Vec<(String, String)> },
+  Bare(Vec<(String, String)>),
+}
+
+/// Key/value dump of the `System.Account` storage map, as returned by
+/// `state_getPairs("0x26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+  /// Hex-encoded storage keys and SCALE-encoded `AccountInfo` values.
+  pub pairs: Vec<(String, String)>,
+}
+
+impl Snapshot {
+  /// Parses a snapshot from JSON, given either as a `[[key, value], ...]` array or as the
+  /// JSON-RPC response holding it under `result`.
+  pub fn from_json(data: &str) -> Result<Self, AllocationError> {
+    // Surface syntax errors with their line before trying both layouts.
+    serde_json::from_str::<serde::de::IgnoredAny>(data)?;
+    let pairs = match serde_json::from_str(data)? {
+      Pairs::Response { result } => result,
+      Pairs::Bare(pairs) => pairs,
+    };
+    Ok(Snapshot { pairs })
+  }
+
+  pub fn from_path(path: impl AsRef<Path>) -> Result<Self, AllocationError> {
+    let path = path.as_ref();
+    let data = fs::read_to_string(path)
+      .map_err(|source| AllocationError::Open { path: path.to_path_buf(), source })?;
+    Self::from_json(&data)
+  }
+
+  /// Decodes every account with a balance of at least `min_balance`, in snapshot order.
+  pub fn parse(&self, min_balance: Balance) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+    let mut balances = vec![];
+    for (index, (key, value)) in self.pairs.iter().enumerate() {
+      let invalid = |reason| AllocationError::InvalidSnapshotEntry { index, reason };
+      let key = hex::decode(key.trim_start_matches("0x")).map_err(|_| invalid("key is not hex"))?;
+      let value = hex::decode(value.trim_start_matches("0x")).map_err(|_| invalid("value is not hex"))?;
+      let (account, balance) = decode_account_pair(&key, &value).map_err(invalid)?;
+      if balance >= min_balance {
+        balances.push((account, balance));
+      }
+    }
+    Ok(balances)
+  }
+}
+
+/// Reads the snapshot at `path` and keeps the accounts with a balance of at least `min_balance`.
+///
+/// The result can be passed to [`crate::get_allocation`] as the endowed accounts, so that an
+/// allocation file is merged on top of the existing chain state.
+pub fn read_snapshot(path: impl AsRef<Path>, min_balance: Balance) -> Result<Vec<(AccountId32, Balance)>, AllocationError> {
+  Snapshot::from_path(path)?.parse(min_balance)
+}
+
+/// Storage prefix of the `System.Account` map, `twox_128("System") ++ twox_128("Account")`.
+pub fn system_account_prefix() -> [u8; 32] {
+  let mut prefix = [0; 32];
+  prefix[..16].copy_from_slice(&twox_128(b"System"));
+  prefix[16..].copy_from_slice(&twox_128(b"Account"));
+  prefix
+}
+
+/// Decodes a `System.Account` storage entry into the account and its free plus reserved balance.
+///
+/// The key is the map prefix followed by the `Blake2_128Concat` hash of the account, which is
+/// checked against the account it ends with.
+pub fn decode_account_pair(key: &[u8], value: &[u8]) -> Result<(AccountId32, Balance), &'static str> {
+  let hashed = key
+    .strip_prefix(&system_account_prefix()[..])
+    .ok_or("key is not a System.Account key")?;
+  if hashed.len() != 16 + 32 {
+    return Err("key does not end with a Blake2_128Concat hashed account");
+  }
+  let (hash, account) = hashed.split_at(16);
+  if blake2_128(account) != hash {
+    return Err("key hash does not match its account");
+  }
+  let mut account_id = [0; 32];
+  account_id.copy_from_slice(account);
+
+  let data = match value.len() {
+    80 => AccountInfo::decode(&mut &value[..]).map(|info| info.data),
+    76 => LegacyAccountInfo::decode(&mut &value[..]).map(|info| info.data),
+    _ => return Err("value is not an encoded AccountInfo"),
+  }
+  .map_err(|_| "value is not an encoded AccountInfo")?;
+  let balance = data.free.checked_add(data.reserved).ok_or("free plus reserved balance overflows")?;
+
+  Ok((AccountId32::from(account_id), balance))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use hex_literal::hex;
+
+  const ALICE: [u8; 32] = hex!["d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"];
+  const BOB: [u8; 32] = hex!["8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"];
+  const CHARLIE: [u8; 32] = hex!["90b5ab205c6974c9ea841be688864633dc9ca8a357843eeacf2314649965fe22"];
+  const DHX: Balance = 1_000_000_000_000_000_000;
+
+  fn fixture(name: &str) -> Snapshot {
+    Snapshot::from_path(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(name)).unwrap()
+  }
+
+  #[test]
+  fn prefix_matches_well_known_key() {
+    assert_eq!(system_account_prefix(), hex!["26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9"]);
+  }
+
+  #[test]
+  fn decodes_json_rpc_response() {
+    let balances = fixture("system_account_pairs.json").parse(0).unwrap();
+
+    assert_eq!(balances, vec![
+      (AccountId32::from(ALICE), 1005 * DHX),
+      (AccountId32::from(BOB), 250 * DHX),
+      (AccountId32::from(CHARLIE), DHX / 2),
+      (AccountId32::from([0x11; 32]), 1),
+    ]);
+  }
+
+  #[test]
+  fn filters_by_min_balance() {
+    let balances = fixture("system_account_pairs.json").parse(DHX).unwrap();
+
+    let accounts: Vec<_> = balances.into_iter().map(|(account, _)| account).collect();
+    assert_eq!(accounts, vec![AccountId32::from(ALICE), AccountId32::from(BOB)]);
+  }
+
+  #[test]
+  fn decodes_legacy_account_info() {
+    let balances = fixture("system_account_pairs_legacy.json").parse(0).unwrap();
+
+    assert_eq!(balances, vec![(AccountId32::from(ALICE), 1005 * DHX), (AccountId32::from(BOB), 250 * DHX)]);
+  }
+
+  #[test]
+  fn rejects_foreign_and_tampered_keys() {
+    let snapshot = fixture("system_account_pairs.json");
+    let (key, value) = &snapshot.pairs[0];
+    let key = hex::decode(key.trim_start_matches("0x")).unwrap();
+    let value = hex::decode(value.trim_start_matches("0x")).unwrap();
+
+    let mut foreign = key.clone();
+    foreign[0] ^= 1;
+    assert_eq!(decode_account_pair(&foreign, &value), Err("key is not a System.Account key"));
+
+    let mut tampered = key.clone();
+    tampered[key.len() - 1] ^= 1;
+    assert_eq!(decode_account_pair(&tampered, &value), Err("key hash does not match its account"));
+
+    assert_eq!(decode_account_pair(&key, &value[1..]), Err("value is not an encoded AccountInfo"));
+  }
+
+  #[test]
+  fn reports_entry_index() {
+    let snapshot = Snapshot::from_json(r#"[["0x26aa", "0x00"]]"#).unwrap();
+
+    assert!(matches!(snapshot.parse(0), Err(AllocationError::InvalidSnapshotEntry { index: 0, .. })));
+  }
+}
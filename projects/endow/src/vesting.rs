@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use sp_runtime::AccountId32;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use crate::{parse_account, Allocation, AllocationError, AllocationSection, Balance, InputFormat, TokenFormat, Violation};
+
+pub type BlockNumber = u32;
+
+/// Raw vesting entry of an allocation file, before the account and amounts are parsed.
+///
+/// ```json
+/// { "account": "5FmxcuFwGK7kPmQCB3zhk3HtxxJUyb3WjxosF8jvnkrVRLUG", "amount": "5,000 DHX",
+///   "start": 0, "perBlock": "0.5 DHX", "cliff": 100800 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct VestingEntry {
+  pub account: String,
+  /// Part of the account's balance that is locked at genesis.
+  pub amount: String,
+  /// Block the schedule starts at.
+  pub start: BlockNumber,
+  /// Amount unlocked every block once the cliff has passed.
+  pub per_block: String,
+  /// Number of blocks after `start` during which nothing unlocks.
+  #[serde(default)]
+  pub cliff: BlockNumber,
+}
+
+/// A vesting schedule: `locked` unlocks by `per_block` every block from `start + cliff` on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vesting {
+  pub account: AccountId32,
+  pub locked: Balance,
+  pub start: BlockNumber,
+  pub per_block: Balance,
+  pub cliff: BlockNumber,
+}
+
+impl Vesting {
+  /// Block at which the first amount unlocks, `None` if it does not fit a block number.
+  pub fn begin(&self) -> Option<BlockNumber> {
+    self.start.checked_add(self.cliff)
+  }
+
+  /// Number of blocks until everything is unlocked, `None` if `per_block` is zero or the length
+  /// does not fit a block number.
+  pub fn length(&self) -> Option<BlockNumber> {
+    if self.per_block == 0 {
+      return None;
+    }
+    // Rounds up, so a partial last block still counts.
+    let length = match self.locked {
+      0 => 0,
+      locked => (locked - 1) / self.per_block + 1,
+    };
+    BlockNumber::try_from(length).ok()
+  }
+}
+
+impl Allocation {
+  /// Parses every vesting entry, in file order.
+  pub fn parse_vesting(&self, token: &TokenFormat) -> Result<Vec<Vesting>, AllocationError> {
+    self.vesting
+      .iter()
+      .enumerate()
+      .map(|(index, entry)| {
+        let account = parse_account(&entry.account).map_err(|reason| AllocationError::InvalidVestingAccount {
+          index,
+          account: entry.account.clone(),
+          reason,
+        })?;
+        let amount = |field, amount: &String| {
+          token.parse(amount).map_err(|source| AllocationError::InvalidVestingAmount {
+            index,
+            field,
+            amount: amount.clone(),
+            source,
+          })
+        };
+        Ok(Vesting {
+          account,
+          locked: amount("amount", &entry.amount)?,
+          start: entry.start,
+          per_block: amount("perBlock", &entry.per_block)?,
+          cliff: entry.cliff,
+        })
+      })
+      .collect()
+  }
+}
+
+/// Balances and vesting schedules of an allocation file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParsedAllocation {
+  /// Balances by section, a single `balances` section for CSV and SCALE files.
+  pub sections: Vec<AllocationSection>,
+  pub vesting: Vec<Vesting>,
+}
+
+impl ParsedAllocation {
+  /// The balances of every section, in file order.
+  pub fn balances(&self) -> Vec<(AccountId32, Balance)> {
+    self.sections.iter().flat_map(|section| section.balances.iter().cloned()).collect()
+  }
+}
+
+/// Reads the allocation file at `path` like [`read_balances`](crate::read_balances), together with
+/// its vesting schedules, parsing the file only once.
+///
+/// Only JSON allocations carry vesting, CSV and SCALE files have none.
+pub fn read_balances_with_vesting(
+  path: impl AsRef<Path>,
+  token: &TokenFormat,
+) -> Result<ParsedAllocation, AllocationError> {
+  let path = path.as_ref();
+  let format = InputFormat::from_path(path)?;
+  let file = File::open(path).map_err(|source| AllocationError::Open { path: path.to_path_buf(), source })?;
+  match format {
+    InputFormat::Json => {
+      let allocation = Allocation::from_reader(file)?;
+      Ok(ParsedAllocation { sections: allocation.parse_sections(token)?, vesting: allocation.parse_vesting(token)? })
+    }
+    InputFormat::Csv | InputFormat::Scale => Ok(ParsedAllocation {
+      sections: vec![AllocationSection { name: "balances", balances: format.read(file, token)? }],
+      vesting: vec![],
+    }),
+  }
+}
+
+/// Checks `vesting` against the final `balances` and converts it into the
+/// `(account, begin, length, liquid)` tuples of the `pallet_vesting` genesis config.
+///
+/// Every schedule needs an account with a balance of at least the locked amount, a non-zero
+/// amount and rate, and a begin and length that fit a block number. Each account can have only one
+/// schedule. All violations are returned together in [`AllocationError::Sanity`].
+///
+/// The genesis config carries no rate. The pallet derives one as `locked / length`, rounded down,
+/// and unlocks that amount for every block elapsed since `begin` until all of `locked` is unlocked.
+/// `length` is rounded up so that `per_block * length >= locked`, which keeps the pallet's rate at
+/// or below `per_block`; when `per_block` does not divide the locked amount, the last unlock can
+/// come later than `begin + length`.
+pub fn genesis_vesting(
+  vesting: &[Vesting],
+  balances: &[(AccountId32, Balance)],
+) -> Result<Vec<(AccountId32, BlockNumber, BlockNumber, Balance)>, AllocationError> {
+  let balance_of: HashMap<&AccountId32, Balance> = balances.iter().map(|(account, balance)| (account, *balance)).collect();
+  let mut violations = vec![];
+  let mut genesis = Vec::with_capacity(vesting.len());
+  let mut indices: HashMap<&AccountId32, Vec<usize>> = HashMap::new();
+
+  for (index, schedule) in vesting.iter().enumerate() {
+    let account = &schedule.account;
+    indices.entry(account).or_default().push(index);
+    let balance = match balance_of.get(account) {
+      Some(balance) => *balance,
+      None => {
+        violations.push(Violation::VestingWithoutBalance { index, account: account.clone() });
+        continue;
+      }
+    };
+    if schedule.locked == 0 || schedule.per_block == 0 {
+      violations.push(Violation::ZeroVesting { index, account: account.clone() });
+      continue;
+    }
+    if schedule.locked > balance {
+      violations.push(Violation::VestingExceedsBalance {
+        index,
+        account: account.clone(),
+        locked: schedule.locked,
+        balance,
+      });
+      continue;
+    }
+    match (schedule.begin(), schedule.length()) {
+      (Some(begin), Some(length)) => genesis.push((account.clone(), begin, length, balance - schedule.locked)),
+      _ => violations.push(Violation::VestingTooLong { index, account: account.clone() }),
+    }
+  }
+
+  let mut duplicates: Vec<_> = indices.into_iter().filter(|(_, indices)| indices.len() > 1).collect();
+  duplicates.sort_by_key(|(_, indices)| indices[0]);
+  violations.extend(
+    duplicates
+      .into_iter()
+      .map(|(account, indices)| Violation::DuplicateVesting { account: account.clone(), indices }),
+  );
+
+  if violations.is_empty() {
+    Ok(genesis)
+  } else {
+    Err(AllocationError::Sanity(violations))
+  }
+}
+
+/// The `vesting` genesis config of `pallet_vesting`, as it appears in a chain spec under
+/// `genesis.runtime.vesting`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VestingSection {
+  /// `(account, begin, length, liquid)` per schedule.
+  pub vesting: Vec<(String, BlockNumber, BlockNumber, Balance)>,
+}
+
+impl VestingSection {
+  /// Encodes the output of [`genesis_vesting`] with the network `ss58_prefix`.
+  pub fn new(vesting: &[(AccountId32, BlockNumber, BlockNumber, Balance)], ss58_prefix: u16) -> Self {
+    let format = Ss58AddressFormat::custom(ss58_prefix);
+    VestingSection {
+      vesting: vesting
+        .iter()
+        .map(|(account, begin, length, liquid)| (account.to_ss58check_with_version(format), *begin, *length, *liquid))
+        .collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn account(byte: u8) -> AccountId32 {
+    AccountId32::from([byte; 32])
+  }
+
+  fn schedule(byte: u8, locked: Balance, per_block: Balance) -> Vesting {
+    Vesting { account: account(byte), locked, start: 10, per_block, cliff: 5 }
+  }
+
+  fn violations(vesting: &[Vesting], balances: &[(AccountId32, Balance)]) -> Vec<Violation> {
+    match genesis_vesting(vesting, balances) {
+      Err(AllocationError::Sanity(violations)) => violations,
+      other => panic!("expected violations, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn parses_vesting_entries() {
+    let json = r#"{
+      "balances": [["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "100 DHX"]],
+      "vesting": [
+        { "account": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+          "amount": "60 DHX", "start": 7, "perBlock": "0.5 DHX", "cliff": 3 }
+      ]
+    }"#;
+    let allocation = Allocation::from_reader(json.as_bytes()).unwrap();
+    let unit = TokenFormat::default().unit().unwrap();
+
+    assert_eq!(allocation.parse_vesting(&TokenFormat::default()).unwrap(), vec![Vesting {
+      account: account(0xaa),
+      locked: 60 * unit,
+      start: 7,
+      per_block: unit / 2,
+      cliff: 3,
+    }]);
+  }
+
+  #[test]
+  fn vesting_is_optional() {
+    let json = r#"{ "balances": [] }"#;
+
+    assert!(Allocation::from_reader(json.as_bytes()).unwrap().vesting.is_empty());
+  }
+
+  #[test]
+  fn reads_balances_and_vesting_together() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("balances.json");
+    std::fs::write(&path, r#"{ "balances": [
+      ["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "100 planck"]
+    ], "vesting": [
+      { "account": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "amount": "60 planck", "start": 10, "perBlock": "7 planck", "cliff": 5 }
+    ] }"#)
+    .unwrap();
+
+    let parsed = read_balances_with_vesting(&path, &TokenFormat::default()).unwrap();
+
+    assert_eq!(parsed.balances(), vec![(account(0xaa), 100)]);
+    assert_eq!(parsed.vesting, vec![schedule(0xaa, 60, 7)]);
+  }
+
+  #[test]
+  fn reports_vesting_entry_of_invalid_amount() {
+    let json = r#"{ "balances": [], "vesting": [
+      { "account": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "amount": "1 planck", "start": 0, "perBlock": "x" }
+    ] }"#;
+    let err = Allocation::from_reader(json.as_bytes()).unwrap().parse_vesting(&TokenFormat::default()).unwrap_err();
+
+    assert!(matches!(err, AllocationError::InvalidVestingAmount { index: 0, field: "perBlock", .. }), "{:?}", err);
+  }
+
+  #[test]
+  fn converts_to_pallet_genesis_tuples() {
+    let balances = vec![(account(1), 100), (account(2), 50)];
+    let vesting = vec![schedule(1, 60, 7)];
+
+    // 60 / 7 rounds up to 9 blocks, starting after the cliff at 10 + 5.
+    assert_eq!(genesis_vesting(&vesting, &balances).unwrap(), vec![(account(1), 15, 9, 40)]);
+  }
+
+  #[test]
+  fn length_covers_amounts_that_do_not_divide_evenly() {
+    for (locked, per_block) in [(60, 7), (61, 7), (62, 7), (1, 2), (10, 3), (1_000_000_000_000_000_001, 1_000_000_000)] {
+      let length = Balance::from(schedule(1, locked, per_block).length().unwrap());
+
+      assert!(per_block * length >= locked, "{} * {} < {}", per_block, length, locked);
+      assert!(per_block * (length - 1) < locked, "{} * {} is more than enough for {}", per_block, length - 1, locked);
+    }
+  }
+
+  #[test]
+  fn reports_all_vesting_violations_together() {
+    let balances = vec![(account(1), 100), (account(2), 50)];
+    let vesting = vec![
+      schedule(2, 51, 1),
+      schedule(3, 1, 1),
+      schedule(1, 10, 0),
+      Vesting { cliff: BlockNumber::MAX, ..schedule(1, 10, 1) },
+      schedule(1, Balance::from(BlockNumber::MAX) + 1, 1),
+    ];
+
+    assert_eq!(violations(&vesting, &balances), vec![
+      Violation::VestingExceedsBalance { index: 0, account: account(2), locked: 51, balance: 50 },
+      Violation::VestingWithoutBalance { index: 1, account: account(3) },
+      Violation::ZeroVesting { index: 2, account: account(1) },
+      Violation::VestingTooLong { index: 3, account: account(1) },
+      Violation::VestingExceedsBalance { index: 4, account: account(1), locked: 4_294_967_296, balance: 100 },
+      Violation::DuplicateVesting { account: account(1), indices: vec![2, 3, 4] },
+    ]);
+  }
+
+  #[test]
+  fn reports_length_overflow() {
+    let locked = Balance::from(BlockNumber::MAX) + 1;
+    let balances = vec![(account(1), locked)];
+
+    assert_eq!(violations(&[schedule(1, locked, 1)], &balances), vec![Violation::VestingTooLong {
+      index: 0,
+      account: account(1),
+    }]);
+  }
+}
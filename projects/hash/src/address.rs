@@ -0,0 +1,217 @@
+//! Ethereum account and contract addresses.
+
+use keccak_hash::keccak;
+
+use crate::{HashError, H160, H256};
+
+/// Address of the account controlled by a secp256k1 key, the last 20 bytes of the keccak-256 of
+/// its public key.
+///
+/// The key is uncompressed: 65 bytes starting with `0x04`, or the 64 bytes of `x` and `y` without
+/// that prefix.
+pub fn public_key_to_address(public_key: &[u8]) -> Result<H160, HashError> {
+    let coordinates = match public_key {
+        [0x04, coordinates @ ..] if coordinates.len() == 64 => coordinates,
+        coordinates if coordinates.len() == 64 => coordinates,
+        [0x02 | 0x03, ..] if public_key.len() == 33 => {
+            return Err(HashError::InvalidPublicKey { reason: "compressed keys are not supported" })
+        }
+        _ => {
+            return Err(HashError::InvalidPublicKey { reason: "expected 65 bytes starting with 0x04, or 64 bytes" })
+        }
+    };
+    Ok(last_20_bytes(keccak(coordinates)))
+}
+
+/// Address of a contract deployed with `CREATE`, from the sender and its nonce at deployment.
+pub fn create_address(sender: &H160, nonce: u64) -> H160 {
+    // RLP of the list [sender, nonce]: both items are short, so the list is too.
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+    let mut rlp = vec![0; 2];
+    rlp[1] = 0x80 + 20;
+    rlp.extend_from_slice(sender.as_bytes());
+    match nonce_bytes {
+        [] => rlp.push(0x80),
+        [byte] if *byte < 0x80 => rlp.push(*byte),
+        bytes => {
+            rlp.push(0x80 + bytes.len() as u8);
+            rlp.extend_from_slice(bytes);
+        }
+    }
+    rlp[0] = 0xc0 + (rlp.len() - 1) as u8;
+    last_20_bytes(keccak(rlp))
+}
+
+/// Address of a contract deployed with `CREATE2` (EIP-1014), which depends on the deployer, the
+/// salt and the init code but not on any nonce.
+pub fn create2_address(deployer: &H160, salt: &H256, init_code: &[u8]) -> H160 {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(deployer.as_bytes());
+    preimage.extend_from_slice(salt.as_bytes());
+    preimage.extend_from_slice(keccak(init_code).as_bytes());
+    last_20_bytes(keccak(preimage))
+}
+
+/// Parses a `CREATE2` salt given as 64 hex digits, with or without `0x`.
+pub fn parse_salt(salt: &str) -> Result<H256, HashError> {
+    let invalid = |reason| HashError::InvalidSalt { salt: salt.to_string(), reason };
+    let digits = salt.strip_prefix("0x").unwrap_or(salt);
+    if digits.len() != 64 {
+        return Err(invalid("expected 32 bytes as 64 hex digits"));
+    }
+    let mut parsed = H256::zero();
+    hex::decode_to_slice(digits, parsed.as_bytes_mut()).map_err(|_| invalid("not hex"))?;
+    Ok(parsed)
+}
+
+fn last_20_bytes(hash: H256) -> H160 {
+    H160::from_slice(&hash.as_bytes()[12..])
+}
+
+/// Encodes `address` with the EIP-55 mixed-case checksum, e.g.
+/// `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`.
+pub fn to_checksum(address: &H160) -> String {
+    let lowercase = hex::encode(address);
+    let hash = keccak(lowercase.as_bytes());
+    let checksummed: String = lowercase
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            // A letter is uppercase if the matching nibble of the hash of the lowercase hex is 8 or more.
+            let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0x0f };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+/// Parses an address given as 40 hex digits, with or without `0x`.
+///
+/// Mixed-case input must carry a valid EIP-55 checksum. All lowercase or all uppercase input has
+/// no checksum to verify and is accepted as is.
+pub fn parse_address(address: &str) -> Result<H160, HashError> {
+    let invalid = |reason| HashError::InvalidAddress { address: address.to_string(), reason };
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    if digits.len() != 40 {
+        return Err(invalid("expected 40 hex digits"));
+    }
+    let mut parsed = H160::zero();
+    hex::decode_to_slice(digits, parsed.as_bytes_mut()).map_err(|_| invalid("not hex"))?;
+    let has_lowercase = digits.bytes().any(|b| b.is_ascii_lowercase());
+    let has_uppercase = digits.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lowercase && has_uppercase && to_checksum(&parsed)[2..] != *digits {
+        return Err(invalid("invalid EIP-55 checksum"));
+    }
+    Ok(parsed)
+}
+
+/// Whether `address` is exactly the EIP-55 encoding of itself, `0x` included.
+pub fn is_checksummed(address: &str) -> bool {
+    matches!(parse_address(address), Ok(parsed) if to_checksum(&parsed) == address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex: &str) -> H160 {
+        parse_address(hex).unwrap()
+    }
+
+    #[test]
+    fn derives_address_from_public_key() {
+        // The public key of the secret key 1, the secp256k1 generator point.
+        let generator = hex::decode(
+            "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+             483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+        )
+        .unwrap();
+        let expected = address("0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf");
+
+        assert_eq!(public_key_to_address(&generator).unwrap(), expected);
+        assert_eq!(public_key_to_address(&generator[1..]).unwrap(), expected);
+        assert!(matches!(public_key_to_address(&generator[..33]), Err(HashError::InvalidPublicKey { .. })));
+        let mut compressed = generator[..33].to_vec();
+        compressed[0] = 0x02;
+        assert!(matches!(public_key_to_address(&compressed), Err(HashError::InvalidPublicKey { .. })));
+    }
+
+    #[test]
+    fn encodes_eip55_checksums() {
+        // Examples from EIP-55.
+        for checksummed in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let parsed = parse_address(&checksummed.to_lowercase()).unwrap();
+
+            assert_eq!(to_checksum(&parsed), checksummed);
+            assert_eq!(parse_address(checksummed).unwrap(), parsed);
+            assert!(is_checksummed(checksummed));
+            assert!(!is_checksummed(&checksummed.to_lowercase()));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        for invalid in [
+            "0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg",
+        ] {
+            assert!(matches!(parse_address(invalid), Err(HashError::InvalidAddress { .. })), "{}", invalid);
+        }
+        assert!(parse_address("5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED").is_ok());
+    }
+
+    #[test]
+    fn computes_create_addresses() {
+        let sender = address("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+
+        assert_eq!(create_address(&sender, 0), address("0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"));
+        assert_eq!(create_address(&sender, 1), address("0x343c43a37d37dff08ae8c4a11544c718abb4fcf8"));
+        assert_eq!(create_address(&sender, 2), address("0xf778b86fa74e846c4f0a1fbd1335fe81c00a0c91"));
+        assert_eq!(create_address(&sender, 3), address("0xfffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"));
+        // Nonces of 0x80 and up are RLP encoded with a length prefix.
+        assert_ne!(create_address(&sender, 0x80), create_address(&sender, 0x7f));
+    }
+
+    #[test]
+    fn computes_create2_addresses() {
+        // Examples from EIP-1014.
+        let zero = H160::zero();
+        let deadbeef = address("0xdeadbeef00000000000000000000000000000000");
+
+        assert_eq!(
+            create2_address(&zero, &H256::zero(), &[0x00]),
+            address("0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38")
+        );
+        assert_eq!(
+            create2_address(&deadbeef, &H256::zero(), &[0x00]),
+            address("0xB928f69Bb1D91Cd65274e3c79d8986362984fDA3")
+        );
+        assert_eq!(
+            create2_address(&zero, &H256::zero(), &[]),
+            address("0xE33C0C7F7df4809055C3ebA6c09CFe4BaF1BD9e0")
+        );
+    }
+
+    #[test]
+    fn parses_salts() {
+        let salt = "0x000000000000000000000000feed000000000000000000000000000000000000";
+
+        assert_eq!(parse_salt(salt).unwrap().as_bytes()[12..14], [0xfe, 0xed]);
+        assert_eq!(parse_salt(&salt[2..]).unwrap(), parse_salt(salt).unwrap());
+        for invalid in ["0xfeed", &salt.replace('f', "g")] {
+            assert!(matches!(parse_salt(invalid), Err(HashError::InvalidSalt { .. })), "{}", invalid);
+        }
+    }
+}
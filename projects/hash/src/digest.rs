@@ -0,0 +1,239 @@
+//! 256 bit digests of byte streams.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use tiny_keccak::{Hasher as _, Keccak, Sha3};
+
+use crate::{HashError, H256};
+
+/// Size of the chunks streamed through a hasher.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Ethereum's keccak-256, with the original Keccak padding.
+    #[default]
+    Keccak256,
+    /// FIPS 202 SHA3-256.
+    Sha3_256,
+    /// BLAKE2b with a 32 byte output, as used by Substrate.
+    Blake2b256,
+}
+
+impl Algorithm {
+    pub const NAMES: [&'static str; 3] = ["keccak256", "sha3-256", "blake2b-256"];
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Algorithm::Keccak256 => Self::NAMES[0],
+            Algorithm::Sha3_256 => Self::NAMES[1],
+            Algorithm::Blake2b256 => Self::NAMES[2],
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keccak256" => Ok(Algorithm::Keccak256),
+            "sha3-256" => Ok(Algorithm::Sha3_256),
+            "blake2b-256" => Ok(Algorithm::Blake2b256),
+            _ => Err(format!("unknown algorithm {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+/// Text encoding of a digest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Lowercase hex without `0x`.
+    #[default]
+    Hex,
+    /// Standard base64 with padding.
+    Base64,
+}
+
+impl Encoding {
+    pub const NAMES: [&'static str; 2] = ["hex", "base64"];
+
+    pub fn encode(&self, digest: &H256) -> String {
+        match self {
+            Encoding::Hex => hex::encode(digest),
+            Encoding::Base64 => BASE64.encode(digest),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Hex => Self::NAMES[0],
+            Encoding::Base64 => Self::NAMES[1],
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            _ => Err(format!("unknown encoding {:?}, expected one of {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+/// Decodes a digest given as hex, with or without `0x`, or as base64.
+pub fn decode_digest(digest: &str) -> Result<H256, HashError> {
+    if let Ok(hash) = crate::parse_block_hash(digest) {
+        return Ok(hash);
+    }
+    match BASE64.decode(digest).map(<[u8; 32]>::try_from) {
+        Ok(Ok(bytes)) => Ok(H256(bytes)),
+        _ => Err(HashError::InvalidDigest { digest: digest.to_string() }),
+    }
+}
+
+/// Incremental digest, fed with [`Hasher::update`].
+pub struct Hasher(State);
+
+enum State {
+    Keccak(Keccak),
+    Sha3(Sha3),
+    Blake2b(blake2b_simd::State),
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Keccak256 => Hasher(State::Keccak(Keccak::v256())),
+            Algorithm::Sha3_256 => Hasher(State::Sha3(Sha3::v256())),
+            Algorithm::Blake2b256 => Hasher(State::Blake2b(blake2b_simd::Params::new().hash_length(32).to_state())),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match &mut self.0 {
+            State::Keccak(keccak) => keccak.update(bytes),
+            State::Sha3(sha3) => sha3.update(bytes),
+            State::Blake2b(state) => {
+                state.update(bytes);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> H256 {
+        let mut digest = H256::zero();
+        match self.0 {
+            State::Keccak(keccak) => keccak.finalize(digest.as_bytes_mut()),
+            State::Sha3(sha3) => sha3.finalize(digest.as_bytes_mut()),
+            State::Blake2b(state) => digest.as_bytes_mut().copy_from_slice(state.finalize().as_bytes()),
+        }
+        digest
+    }
+}
+
+pub fn digest(algorithm: Algorithm, bytes: &[u8]) -> H256 {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Digests everything `reader` yields, a chunk at a time, so files of any size fit in memory.
+pub fn digest_reader(algorithm: Algorithm, mut reader: impl Read) -> io::Result<H256> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(read) => hasher.update(&chunk[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Splits a line of a digest list, `<digest> <path>` as written by the CLI or `sha256sum`, into
+/// the decoded digest and the path. A `*` before the path, marking binary mode, is dropped.
+pub fn parse_check_line(line: &str) -> Result<(H256, &str), HashError> {
+    let invalid = || HashError::InvalidCheckLine { line: line.to_string() };
+    let (digest, path) = line.trim_end().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let path = path.trim_start();
+    let path = path.strip_prefix('*').unwrap_or(path);
+    if path.is_empty() {
+        return Err(invalid());
+    }
+    Ok((decode_digest(digest)?, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_digest(algorithm: Algorithm, bytes: &[u8]) -> String {
+        Encoding::Hex.encode(&digest(algorithm, bytes))
+    }
+
+    #[test]
+    fn matches_reference_digests() {
+        assert_eq!(
+            hex_digest(Algorithm::Keccak256, b""),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex_digest(Algorithm::Sha3_256, b""),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_eq!(
+            hex_digest(Algorithm::Blake2b256, b""),
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        assert_eq!(
+            hex_digest(Algorithm::Keccak256, b"abc"),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        assert_eq!(digest(Algorithm::Keccak256, b"abc"), keccak_hash::keccak(b"abc"));
+    }
+
+    #[test]
+    fn streams_in_chunks() {
+        let bytes: Vec<u8> = (0..3 * CHUNK_SIZE + 17).map(|i| i as u8).collect();
+
+        for algorithm in [Algorithm::Keccak256, Algorithm::Sha3_256, Algorithm::Blake2b256] {
+            assert_eq!(digest_reader(algorithm, &bytes[..]).unwrap(), digest(algorithm, &bytes), "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn round_trips_encodings() {
+        let hash = digest(Algorithm::Sha3_256, b"abc");
+
+        for encoding in [Encoding::Hex, Encoding::Base64] {
+            assert_eq!(decode_digest(&encoding.encode(&hash)).unwrap(), hash);
+        }
+        assert_eq!(Encoding::Base64.encode(&H256::zero()), "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+        assert!(matches!(decode_digest("AAAA"), Err(HashError::InvalidDigest { .. })));
+    }
+
+    #[test]
+    fn parses_digest_list_lines() {
+        let hash = digest(Algorithm::Keccak256, b"abc");
+        let line = format!("{}  dir/with space.bin", Encoding::Hex.encode(&hash));
+
+        assert_eq!(parse_check_line(&line).unwrap(), (hash, "dir/with space.bin"));
+        assert_eq!(parse_check_line(&format!("{} *a.bin\n", Encoding::Base64.encode(&hash))).unwrap(), (hash, "a.bin"));
+        assert!(matches!(parse_check_line("no-path"), Err(HashError::InvalidCheckLine { .. })));
+    }
+}
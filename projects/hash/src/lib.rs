@@ -0,0 +1,208 @@
+//! Randomness derived from block hashes.
+//!
+//! A block hash is parsed from hex into an [`H256`] with [`parse_block_hash`], and [`extract`]
+//! reads a number of bits of it as an unsigned integer. [`pick_index`] turns it into a uniformly
+//! distributed index. The [`digest`] module hashes byte streams with keccak-256, SHA3-256 or
+//! BLAKE2b-256, and the [`address`] module derives and checksums Ethereum addresses.
+//!
+//! ```
+//! use hash::{extract, parse_block_hash};
+//!
+//! let hash = parse_block_hash("0xaef6eca62ae61934a7ab5ad3814f6e319abd3e4e4aa1a3386466ad197d1c4dea").unwrap();
+//!
+//! assert_eq!(extract::<u16>(&hash, 30..32, 16).unwrap(), 0x4dea);
+//! assert_eq!(extract::<u32>(&hash, 28..32, 20).unwrap(), 0xc4dea);
+//! assert!(hash::pick_index(&hash, 10).unwrap() < 10);
+//! ```
+
+use std::io;
+use std::mem::size_of;
+use std::ops::Range;
+
+pub use ethereum_types::{H160, H256};
+use keccak_hash::keccak;
+
+pub mod address;
+pub mod digest;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HashError {
+    #[error("block hash must be 64 hex digits, found {digits}")]
+    InvalidLength { digits: usize },
+    #[error("invalid hex {input:?}")]
+    InvalidHex {
+        input: String,
+        #[source]
+        source: hex::FromHexError,
+    },
+    #[error("byte range {start}..{end} must be non-empty and within the 32 byte hash")]
+    InvalidRange { start: usize, end: usize },
+    #[error("cannot take {bits} bits from {bytes} bytes into a {width} bit integer")]
+    TooManyBits { bits: u32, bytes: usize, width: usize },
+    #[error("cannot pick an index from an empty range")]
+    EmptyRange,
+    #[error("invalid digest {digest:?}, expected 32 bytes as hex or base64")]
+    InvalidDigest { digest: String },
+    #[error("invalid digest list line {line:?}, expected a digest and a path")]
+    InvalidCheckLine { line: String },
+    #[error("invalid public key: {reason}")]
+    InvalidPublicKey { reason: &'static str },
+    #[error("invalid address {address:?}: {reason}")]
+    InvalidAddress { address: String, reason: &'static str },
+    #[error("invalid salt {salt:?}: {reason}")]
+    InvalidSalt { salt: String, reason: &'static str },
+    #[error("cannot read {path}")]
+    Read {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Parses a 32 byte block hash given as 64 hex digits, with or without `0x`.
+pub fn parse_block_hash(input: &str) -> Result<H256, HashError> {
+    let digits = input.strip_prefix("0x").unwrap_or(input);
+    if digits.len() != 64 {
+        return Err(HashError::InvalidLength { digits: digits.len() });
+    }
+    let mut hash = H256::zero();
+    hex::decode_to_slice(digits, hash.as_bytes_mut())
+        .map_err(|source| HashError::InvalidHex { input: input.to_string(), source })?;
+    Ok(hash)
+}
+
+/// Reads the bytes in `range` of `hash` as a big-endian integer and keeps its lowest `bits` bits.
+///
+/// The range may span at most as many bytes as `T` holds, and at most 8 even for wider types, and
+/// `bits` may not exceed the bits in the range, so every returned bit comes from the hash.
+pub fn extract<T: TryFrom<u64>>(hash: &H256, range: Range<usize>, bits: u32) -> Result<T, HashError> {
+    let Range { start, end } = range;
+    // The bytes are assembled in a `u64`, whatever the width of `T`.
+    let width = (8 * size_of::<T>()).min(64);
+    let bytes = hash.as_bytes().get(start..end).filter(|bytes| !bytes.is_empty());
+    let bytes = bytes.ok_or(HashError::InvalidRange { start, end })?;
+    let too_many_bits = HashError::TooManyBits { bits, bytes: bytes.len(), width };
+    if bits == 0 || bits as usize > 8 * bytes.len() || 8 * bytes.len() > width {
+        return Err(too_many_bits);
+    }
+    let value = bytes.iter().fold(0u64, |value, &byte| value << 8 | u64::from(byte));
+    let mask = u64::MAX >> (64 - bits);
+    T::try_from(value & mask).map_err(|_| too_many_bits)
+}
+
+/// Picks an index in `0..max` from `hash` without modulo bias.
+///
+/// Each 8 byte word of the hash is masked to the bits needed for `max - 1` and taken if it is
+/// below `max`. When all four are rejected, the hash is replaced by its keccak-256 and the words
+/// of that are tried, and so on. Every candidate is rejected with a probability below 1/2.
+pub fn pick_index(hash: &H256, max: u64) -> Result<u64, HashError> {
+    let bits = match max {
+        0 => return Err(HashError::EmptyRange),
+        1 => return Ok(0),
+        max => 64 - (max - 1).leading_zeros(),
+    };
+    let mut hash = *hash;
+    loop {
+        for word in 0..4 {
+            let candidate: u64 = extract(&hash, 8 * word..8 * word + 8, bits)?;
+            if candidate < max {
+                return Ok(candidate);
+            }
+        }
+        hash = keccak(hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_HASH: &str = "aef6eca62ae61934a7ab5ad3814f6e319abd3e4e4aa1a3386466ad197d1c4dea";
+
+    #[test]
+    fn parses_prefixed_and_bare_hex() {
+        let hash = parse_block_hash(BLOCK_HASH).unwrap();
+
+        assert_eq!(parse_block_hash(&format!("0x{}", BLOCK_HASH)).unwrap(), hash);
+        assert_eq!(parse_block_hash(&BLOCK_HASH.to_uppercase()).unwrap(), hash);
+        assert_eq!(hash.as_bytes()[0], 0xae);
+        assert_eq!(hash.as_bytes()[31], 0xea);
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        assert!(matches!(parse_block_hash("0xaef6").unwrap_err(), HashError::InvalidLength { digits: 4 }));
+        assert!(matches!(parse_block_hash(&"g".repeat(64)).unwrap_err(), HashError::InvalidHex { .. }));
+    }
+
+    #[test]
+    fn extracts_values_above_i16_max() {
+        let hash = parse_block_hash(&"ff".repeat(32)).unwrap();
+
+        assert_eq!(extract::<u16>(&hash, 30..32, 16).unwrap(), u16::MAX);
+        assert_eq!(extract::<u32>(&hash, 0..4, 32).unwrap(), u32::MAX);
+        assert_eq!(extract::<u64>(&hash, 24..32, 64).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn extracts_the_lowest_bits_of_a_range() {
+        let hash = parse_block_hash(BLOCK_HASH).unwrap();
+
+        assert_eq!(extract::<u16>(&hash, 30..32, 16).unwrap(), 0x4dea);
+        assert_eq!(extract::<u16>(&hash, 30..32, 12).unwrap(), 0xdea);
+        assert_eq!(extract::<u16>(&hash, 0..1, 1).unwrap(), 0);
+        assert_eq!(extract::<u32>(&hash, 0..3, 24).unwrap(), 0xaef6ec);
+        assert_eq!(extract::<u64>(&hash, 0..8, 64).unwrap(), 0xaef6eca62ae61934);
+    }
+
+    #[test]
+    fn rejects_ranges_and_bits_that_do_not_fit() {
+        let hash = parse_block_hash(BLOCK_HASH).unwrap();
+
+        for (range, bits) in [(30..33, 16), (4..4, 8), (32..34, 8)] {
+            assert!(matches!(extract::<u16>(&hash, range, bits), Err(HashError::InvalidRange { .. })));
+        }
+        for (range, bits) in [(29..32, 16), (31..32, 9), (30..32, 0)] {
+            assert!(matches!(extract::<u16>(&hash, range, bits), Err(HashError::TooManyBits { .. })));
+        }
+        // Wider types are still limited to 8 bytes and 64 bits.
+        for (range, bits) in [(16..32, 128), (16..32, 64), (23..32, 65)] {
+            assert!(matches!(extract::<u128>(&hash, range, bits), Err(HashError::TooManyBits { width: 64, .. })));
+        }
+        assert_eq!(extract::<u128>(&hash, 24..32, 64).unwrap(), 0x6466ad197d1c4dea);
+    }
+
+    #[test]
+    fn picks_indices_within_range() {
+        let hash = parse_block_hash(BLOCK_HASH).unwrap();
+
+        assert_eq!(pick_index(&hash, 1).unwrap(), 0);
+        // 0xaef6eca62ae61934 masked to 7 bits is 0x34.
+        assert_eq!(pick_index(&hash, 100).unwrap(), 0x34);
+        assert!(pick_index(&hash, u64::MAX).unwrap() < u64::MAX);
+        assert!(matches!(pick_index(&hash, 0), Err(HashError::EmptyRange)));
+    }
+
+    #[test]
+    fn rehashes_when_every_word_is_rejected() {
+        // Every word masked to 2 bits is 3, which is out of 0..3.
+        let hash = parse_block_hash(&"ff".repeat(32)).unwrap();
+
+        assert_eq!(pick_index(&hash, 3).unwrap(), pick_index(&keccak(hash), 3).unwrap());
+    }
+
+    #[test]
+    fn picks_indices_uniformly() {
+        // 6 needs 3 bits, so a plain `% 6` of them would pick 0 and 1 twice as often as the rest.
+        let mut counts = [0u32; 6];
+        let mut hash = H256::zero();
+        for _ in 0..60_000 {
+            hash = keccak(hash);
+            counts[pick_index(&hash, 6).unwrap() as usize] += 1;
+        }
+
+        for count in counts {
+            assert!((9_500..10_500).contains(&count), "{:?}", counts);
+        }
+    }
+}
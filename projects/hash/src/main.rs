@@ -1,50 +1,168 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process;
 
+use clap::{Parser, Subcommand};
+use hash::address::{create2_address, create_address, parse_address, parse_salt, public_key_to_address, to_checksum};
+use hash::digest::{digest, digest_reader, parse_check_line, Algorithm, Encoding};
+use hash::{extract, parse_block_hash, pick_index, HashError, H256};
 
-// use ethereum_types::H256;
-// use keccak_hash::keccak;
-use std::error::Error;
-use bytes::Bytes;
-use hex::{decode, FromHex};
-use std::str::from_utf8_unchecked;
-use std::string::String;
-
-pub fn last_bytes(slice: &[u8]) -> &[u8] {
-    slice.split_at(slice.len() - 4).1
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
-    // note: Hash is [u8; 32] 32 bytes
-    
-    let block_hash_entropy: &[u8] = "aef6eca62ae61934a7ab5ad3814f6e319abd3e4e4aa1a3386466ad197d1c4dea".as_bytes();
-    assert!(block_hash_entropy.len() == 64, "block hash should be 256 bit block hash");
-    println!("block_hash_entropy: {:?}\n", block_hash_entropy);
-    // https://peterlyons.com/problog/2017/12/rust-converting-bytes-chars-and-strings/
-    let array_of_u8 = last_bytes(&block_hash_entropy);
-    println!("array_of_u8: {:?}\n", array_of_u8);
-    let raw = String::from_utf8_lossy(&array_of_u8);
-    println!("raw: {:?}", raw);
-    // use u16 since max value 65535
-    // let without_prefix = raw.trim_start_matches("0x");
-    let decimal = i16::from_str_radix(&raw, 16);
-    println!("decimal {:?}", decimal);
-
-    // println!("block_hash_entropy: {:?}\n", block_hash_entropy);
-    // let hash = decode(block_hash_entropy);
-    // println!("hash: {:?}\n", hash);
-    // let buffer: &[u8] = &<[u8; 32]>::from_hex(block_hash_entropy)?;
-    // println!("buffer: {:?}\n", buffer);
-
-    // // str::from_utf8(b) from Bytes
-    // let entropy: &str = std::str::from_utf8(&buffer)?;//.expect("invalid buffer length");
-    // println!("entropy: {:?}\n", entropy.to_string());
-    // // get last 5 chars ascii chars that are one byte each
-    // let len = entropy.len();
-    // let sub_entropy = &entropy[len-5..];
-    // println!("sub_entropy: {:?}\n", sub_entropy);
-
-    // let z: &[u8] = &[b'a', b'b'];
-    // println!("z {:?}", z);
-    // let stack_str: &str = std::str::from_utf8(z).unwrap();
-    // println!("stack_str is: {}", stack_str.to_string());
+/// Prints the digests of files, stdin or hex input, one `<digest>  <name>` line each, or verifies
+/// a list of such lines with `--check`.
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Files to digest, streamed in chunks. `-` or no file reads stdin.
+    files: Vec<String>,
+    /// Digest this hex data, with or without 0x, instead of files. May be repeated.
+    #[arg(long = "hex", value_name = "HEX", conflicts_with = "files")]
+    hex_inputs: Vec<String>,
+    /// keccak256, sha3-256 or blake2b-256.
+    #[arg(short, long, default_value_t = Algorithm::Keccak256)]
+    algorithm: Algorithm,
+    /// hex or base64.
+    #[arg(short, long, default_value_t = Encoding::Hex)]
+    encoding: Encoding,
+    /// Verify the files named in this digest list, `-` for stdin, instead of printing digests.
+    /// Digests in the list may be hex or base64.
+    #[arg(short, long, value_name = "LIST", conflicts_with_all = ["files", "hex_inputs"])]
+    check: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Decode a block hash and read its last bytes as u16/u32/u64 entropy.
+    Entropy {
+        /// 32 byte block hash as hex, with or without 0x.
+        block_hash: String,
+        /// Also pick an unbiased index in 0..MAX from the hash.
+        max: Option<u64>,
+    },
+    /// Print the EIP-55 address of an uncompressed secp256k1 public key.
+    Address {
+        /// 65 bytes starting with 04, or 64 bytes, as hex with or without 0x.
+        public_key: String,
+    },
+    /// Validate an address and print it with its EIP-55 checksum.
+    Checksum { address: String },
+    /// Print the address of a contract deployed with CREATE.
+    Create { sender: String, nonce: u64 },
+    /// Print the address of a contract deployed with CREATE2.
+    Create2 {
+        deployer: String,
+        /// 32 byte salt as hex, with or without 0x.
+        salt: String,
+        /// Init code as hex, with or without 0x.
+        init_code: String,
+    },
+}
+
+fn open(path: &str) -> Result<Box<dyn Read>, HashError> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(path).map_err(|source| HashError::Read { path: path.to_string(), source })?;
+    Ok(Box::new(file))
+}
+
+fn digest_path(algorithm: Algorithm, path: &str) -> Result<H256, HashError> {
+    digest_reader(algorithm, open(path)?).map_err(|source| HashError::Read { path: path.to_string(), source })
+}
+
+/// Prints `<path>: OK` or `<path>: FAILED` per listed file and returns whether all matched.
+fn check(algorithm: Algorithm, list: &str) -> Result<bool, HashError> {
+    let read_error = |source| HashError::Read { path: list.to_string(), source };
+    let mut all_match = true;
+    for line in BufReader::new(open(list)?).lines() {
+        let line = line.map_err(read_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (expected, path) = parse_check_line(&line)?;
+        match digest_path(algorithm, path) {
+            Ok(actual) if actual == expected => println!("{}: OK", path),
+            Ok(_) => {
+                println!("{}: FAILED", path);
+                all_match = false;
+            }
+            Err(e) => {
+                println!("{}: FAILED ({})", path, e);
+                all_match = false;
+            }
+        }
+    }
+    Ok(all_match)
+}
+
+fn entropy(block_hash: &str, max: Option<u64>) -> Result<(), HashError> {
+    let hash = parse_block_hash(block_hash)?;
+    println!("block hash: {:?}", hash);
+    // the last 2, 4 and 8 bytes, read as big-endian integers
+    println!("u16: {}", extract::<u16>(&hash, 30..32, 16)?);
+    println!("u32: {}", extract::<u32>(&hash, 28..32, 32)?);
+    println!("u64: {}", extract::<u64>(&hash, 24..32, 64)?);
+    if let Some(max) = max {
+        println!("index in 0..{}: {}", max, pick_index(&hash, max)?);
+    }
+    Ok(())
+}
+
+fn decode_hex(input: &str) -> Result<Vec<u8>, HashError> {
+    let digits = input.strip_prefix("0x").unwrap_or(input);
+    hex::decode(digits).map_err(|source| HashError::InvalidHex { input: input.to_string(), source })
+}
+
+fn run_command(command: Command) -> Result<(), HashError> {
+    let address = match command {
+        Command::Entropy { block_hash, max } => return entropy(&block_hash, max),
+        Command::Address { public_key } => public_key_to_address(&decode_hex(&public_key)?)?,
+        Command::Checksum { address } => parse_address(&address)?,
+        Command::Create { sender, nonce } => create_address(&parse_address(&sender)?, nonce),
+        Command::Create2 { deployer, salt, init_code } => {
+            create2_address(&parse_address(&deployer)?, &parse_salt(&salt)?, &decode_hex(&init_code)?)
+        }
+    };
+    println!("{}", to_checksum(&address));
     Ok(())
 }
+
+fn run(cli: Cli) -> Result<bool, HashError> {
+    if let Some(command) = cli.command {
+        run_command(command)?;
+        return Ok(true);
+    }
+    if let Some(list) = cli.check {
+        return check(cli.algorithm, &list);
+    }
+    for input in &cli.hex_inputs {
+        let bytes = decode_hex(input)?;
+        println!("{}  0x{}", cli.encoding.encode(&digest(cli.algorithm, &bytes)), hex::encode(&bytes));
+    }
+    if !cli.hex_inputs.is_empty() {
+        return Ok(true);
+    }
+    let files = if cli.files.is_empty() { vec!["-".to_string()] } else { cli.files };
+    for path in files {
+        println!("{}  {}", cli.encoding.encode(&digest_path(cli.algorithm, &path)?), path);
+    }
+    Ok(true)
+}
+
+fn main() {
+    match run(Cli::parse()) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            let mut source = e.source();
+            while let Some(cause) = source {
+                eprintln!("  caused by: {}", cause);
+                source = cause.source();
+            }
+            process::exit(2);
+        }
+    }
+}
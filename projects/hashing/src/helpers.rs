@@ -0,0 +1,210 @@
+//! Random selection helpers, generic over any [`RngCore`].
+//!
+//! Every helper samples without modulo bias. The functions in [`crypto`] are the same helpers
+//! restricted to generators marked [`CryptoRng`], for selections that must not be predictable.
+//! With [`seeded_rng`] a run can be reproduced exactly, e.g. in tests.
+
+use rand::distributions::{Distribution, WeightedError, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::{index, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rand_core::RngCore;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SampleError {
+    #[error("cannot pick from an empty range")]
+    Empty,
+    #[error("cannot sample {k} items from {len}")]
+    NotEnoughItems { k: usize, len: usize },
+    #[error("invalid weights: {0}")]
+    InvalidWeights(WeightedError),
+}
+
+/// A generator whose output is determined by `seed`.
+///
+/// `StdRng` may change algorithm between `rand` releases, so the sequence is only reproducible
+/// with the same version.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// A generator seeded with 32 bytes, e.g. a block hash.
+pub fn seeded_rng_from_bytes(seed: [u8; 32]) -> StdRng {
+    StdRng::from_seed(seed)
+}
+
+/// A uniformly distributed index in `0..len`.
+pub fn pick_index<R: RngCore + ?Sized>(rng: &mut R, len: usize) -> Result<usize, SampleError> {
+    match len {
+        0 => Err(SampleError::Empty),
+        // `gen_range` rejects samples that would bias the result, unlike `rng.next_u64() % len`.
+        len => Ok(rng.gen_range(0..len)),
+    }
+}
+
+/// Shuffles `items` in place, every permutation being equally likely.
+pub fn shuffle<R: RngCore + ?Sized, T>(rng: &mut R, items: &mut [T]) {
+    items.shuffle(rng);
+}
+
+/// `k` distinct indices in `0..len`, in random order.
+pub fn sample_k_without_replacement<R: RngCore + ?Sized>(
+    rng: &mut R,
+    len: usize,
+    k: usize,
+) -> Result<Vec<usize>, SampleError> {
+    if k > len {
+        return Err(SampleError::NotEnoughItems { k, len });
+    }
+    Ok(index::sample(rng, len, k).into_vec())
+}
+
+/// An index into `weights`, picked with probability proportional to its weight.
+///
+/// Weights must be non-negative and finite, and at least one must be positive.
+pub fn weighted_choice<R: RngCore + ?Sized>(rng: &mut R, weights: &[f64]) -> Result<usize, SampleError> {
+    let distribution = WeightedIndex::new(weights).map_err(|e| match e {
+        WeightedError::NoItem => SampleError::Empty,
+        e => SampleError::InvalidWeights(e),
+    })?;
+    Ok(distribution.sample(rng))
+}
+
+/// The helpers of the parent module, accepting only cryptographically secure generators.
+pub mod crypto {
+    use rand_core::{CryptoRng, RngCore};
+
+    use super::SampleError;
+
+    pub fn pick_index<R: RngCore + CryptoRng + ?Sized>(rng: &mut R, len: usize) -> Result<usize, SampleError> {
+        super::pick_index(rng, len)
+    }
+
+    pub fn shuffle<R: RngCore + CryptoRng + ?Sized, T>(rng: &mut R, items: &mut [T]) {
+        super::shuffle(rng, items)
+    }
+
+    pub fn sample_k_without_replacement<R: RngCore + CryptoRng + ?Sized>(
+        rng: &mut R,
+        len: usize,
+        k: usize,
+    ) -> Result<Vec<usize>, SampleError> {
+        super::sample_k_without_replacement(rng, len, k)
+    }
+
+    pub fn weighted_choice<R: RngCore + CryptoRng + ?Sized>(rng: &mut R, weights: &[f64]) -> Result<usize, SampleError> {
+        super::weighted_choice(rng, weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    const SAMPLES: usize = 60_000;
+
+    /// Pearson's chi-square statistic of `observed` counts against `expected` probabilities.
+    fn chi_square(observed: &[usize], expected: &[f64]) -> f64 {
+        let total = observed.iter().sum::<usize>() as f64;
+        observed
+            .iter()
+            .zip(expected)
+            .map(|(&observed, &probability)| {
+                let expected = total * probability;
+                (observed as f64 - expected).powi(2) / expected
+            })
+            .sum()
+    }
+
+    /// Counts of each distinct outcome, in a fixed order.
+    fn counts<T: Hash + Eq + Ord>(outcomes: impl Iterator<Item = T>) -> Vec<usize> {
+        let mut counts = HashMap::new();
+        for outcome in outcomes {
+            *counts.entry(outcome).or_insert(0) += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort();
+        counts.into_iter().map(|(_, count)| count).collect()
+    }
+
+    #[test]
+    fn picks_indices_uniformly() {
+        let mut rng = seeded_rng(1);
+        let observed = counts((0..SAMPLES).map(|_| pick_index(&mut rng, 10).unwrap()));
+
+        assert_eq!(observed.len(), 10);
+        // 27.88 is the 0.999 quantile of the chi-square distribution with 9 degrees of freedom.
+        assert!(chi_square(&observed, &[0.1; 10]) < 27.88, "{:?}", observed);
+        assert_eq!(pick_index(&mut rng, 0), Err(SampleError::Empty));
+    }
+
+    #[test]
+    fn shuffles_into_every_permutation_equally() {
+        let mut rng = seeded_rng(2);
+        let observed = counts((0..SAMPLES).map(|_| {
+            let mut items = [0, 1, 2, 3];
+            shuffle(&mut rng, &mut items);
+            items
+        }));
+
+        assert_eq!(observed.len(), 24);
+        // 0.999 quantile with 23 degrees of freedom.
+        assert!(chi_square(&observed, &[1.0 / 24.0; 24]) < 49.73, "{:?}", observed);
+    }
+
+    #[test]
+    fn samples_every_subset_equally() {
+        let mut rng = seeded_rng(3);
+        let observed = counts((0..SAMPLES).map(|_| {
+            let mut sample = sample_k_without_replacement(&mut rng, 5, 2).unwrap();
+            sample.sort();
+            sample
+        }));
+
+        // The 10 subsets of 2 out of 5, 0.999 quantile with 9 degrees of freedom.
+        assert_eq!(observed.len(), 10);
+        assert!(chi_square(&observed, &[0.1; 10]) < 27.88, "{:?}", observed);
+        assert_eq!(sample_k_without_replacement(&mut rng, 5, 5).unwrap().len(), 5);
+        assert_eq!(sample_k_without_replacement(&mut rng, 2, 3), Err(SampleError::NotEnoughItems { k: 3, len: 2 }));
+    }
+
+    #[test]
+    fn chooses_in_proportion_to_weights() {
+        let mut rng = seeded_rng(4);
+        let observed = counts((0..SAMPLES).map(|_| weighted_choice(&mut rng, &[1.0, 2.0, 0.0, 3.0, 4.0]).unwrap()));
+
+        // The zero weight is never chosen; 0.999 quantile with 3 degrees of freedom.
+        assert_eq!(observed.len(), 4);
+        assert!(chi_square(&observed, &[0.1, 0.2, 0.3, 0.4]) < 16.27, "{:?}", observed);
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        let mut rng = seeded_rng(5);
+
+        assert_eq!(weighted_choice(&mut rng, &[]), Err(SampleError::Empty));
+        for invalid in [&[0.0, 0.0][..], &[1.0, -1.0], &[f64::NAN]] {
+            assert!(matches!(weighted_choice(&mut rng, invalid), Err(SampleError::InvalidWeights(_))), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn seeded_runs_are_reproducible() {
+        let run = |seed| {
+            let mut rng = seeded_rng(seed);
+            let mut items: Vec<u32> = (0..20).collect();
+            crypto::shuffle(&mut rng, &mut items);
+            (items, crypto::pick_index(&mut rng, 1_000).unwrap(), crypto::weighted_choice(&mut rng, &[1.0, 1.0]).unwrap())
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).0, run(8).0);
+        let mut from_bytes = seeded_rng_from_bytes([7; 32]);
+        assert_eq!(
+            crypto::sample_k_without_replacement(&mut from_bytes, 100, 3).unwrap(),
+            crypto::sample_k_without_replacement(&mut seeded_rng_from_bytes([7; 32]), 100, 3).unwrap()
+        );
+    }
+}
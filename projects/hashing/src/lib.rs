@@ -0,0 +1,3 @@
+//! Unbiased random selection, see [`helpers`].
+
+pub mod helpers;
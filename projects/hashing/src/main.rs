@@ -1,15 +1,18 @@
+use hashing::helpers::{crypto, pick_index, seeded_rng, shuffle, weighted_choice};
 use rand::prelude::*;
-use rand_core::{CryptoRng, RngCore};
 
-fn pick_usize<R: RngCore, C: CryptoRng + ?Sized>(rng: &mut R, max: usize) -> usize {
-    // use `Rng::gen_range` instead of `%` otherwise it generates biases in results
-    // since the modulo of a uniform distribution makes it not uniform anymore
-    rng.gen_range(0..=max)
-}
-    
 fn main() {
-    let max = 100;
+    let len = 100;
+    // `ThreadRng` is a `CryptoRng`, so it may be used for the secure variants too
     let mut rng = thread_rng();
-    let random_number = pick_usize::<ThreadRng, dyn CryptoRng>(&mut rng, max);
-    println!("{}", random_number);
+    let random_index = crypto::pick_index(&mut rng, len).unwrap();
+    println!("{}", random_index);
+
+    // the same seed always gives the same picks
+    let seed = std::env::args().nth(1).and_then(|seed| seed.parse().ok()).unwrap_or(42);
+    let mut seeded = seeded_rng(seed);
+    let mut items: Vec<usize> = (0..10).collect();
+    shuffle(&mut seeded, &mut items);
+    println!("seed {}: index {}, shuffled {:?}", seed, pick_index(&mut seeded, len).unwrap(), items);
+    println!("weighted choice of [1, 2, 7]: {}", weighted_choice(&mut seeded, &[1.0, 2.0, 7.0]).unwrap());
 }
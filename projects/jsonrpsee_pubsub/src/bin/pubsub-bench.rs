@@ -0,0 +1,218 @@
+//! Load generator for the pubsub server.
+//!
+//! Opens `--clients` WebSocket connections, each holding `--subscriptions` subscriptions to both
+//! `sub_one_param` and `sub_params_two`, and reads notifications for `--duration-secs`.
+//! Notifications must be stamped (see [`jsonrpsee_pubsub::Stamped`]) so that latency can be
+//! measured against the server-side send time and gaps in the sequence numbers counted as drops.
+//!
+//! Without `--url` an in-process server is started, configured with the same flags and config file
+//! as the `jsonrpsee_pubsub` binary, e.g. to compare `--message-buffer-capacity` values.
+
+use std::time::Duration;
+
+use clap::Parser;
+use futures::stream::{self, StreamExt};
+use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
+use jsonrpsee::rpc_params;
+use jsonrpsee::ws_client::WsClientBuilder;
+use jsonrpsee_pubsub::{run_server, unix_micros, Cli, Config, Notification, LETTERS};
+use serde::de::IgnoredAny;
+use tokio::time::{sleep_until, Instant};
+
+#[derive(Debug, Parser)]
+#[command(about = "Load generator for the JSON-RPC pubsub server")]
+struct BenchCli {
+	/// Server to connect to, e.g. `ws://127.0.0.1:9944`. It must run with `--stamp-notifications`.
+	/// Without it an in-process server is started from the server flags below.
+	#[arg(long)]
+	url: Option<String>,
+	/// Number of WebSocket clients.
+	#[arg(long, default_value_t = 10)]
+	clients: usize,
+	/// Number of subscriptions per client to each of `sub_one_param` and `sub_params_two`.
+	#[arg(long, default_value_t = 10)]
+	subscriptions: usize,
+	/// How long to receive notifications for.
+	#[arg(long, default_value_t = 10)]
+	duration_secs: u64,
+	#[command(flatten)]
+	server: Cli,
+}
+
+/// What a single client observed over the run.
+#[derive(Debug, Default)]
+struct ClientReport {
+	received: u64,
+	dropped: u64,
+	errors: u64,
+	/// Difference between receive time and server-side send time, in microseconds.
+	latencies_micros: Vec<u64>,
+}
+
+impl ClientReport {
+	fn merge(&mut self, other: ClientReport) {
+		self.received += other.received;
+		self.dropped += other.dropped;
+		self.errors += other.errors;
+		self.latencies_micros.extend(other.latencies_micros);
+	}
+}
+
+/// Tracks the sequence numbers seen on one subscription.
+#[derive(Debug, Default)]
+struct SeqTracker {
+	next: u64,
+}
+
+impl SeqTracker {
+	/// Returns the number of notifications skipped before `seq`.
+	fn observe(&mut self, seq: u64) -> u64 {
+		let dropped = seq.saturating_sub(self.next);
+		self.next = self.next.max(seq + 1);
+		dropped
+	}
+}
+
+/// Value at percentile `p` (0..=100) of an ascending slice, using the nearest-rank method.
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+	if sorted.is_empty() {
+		return None;
+	}
+	let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+	Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+async fn run_client(url: String, subscriptions: usize, deadline: Instant) -> anyhow::Result<ClientReport> {
+	let client = WsClientBuilder::default().build(&url).await?;
+
+	let mut subs = Vec::with_capacity(subscriptions * 2);
+	for i in 0..subscriptions {
+		let one: Subscription<Notification<IgnoredAny>> = client
+			.subscribe("sub_one_param", rpc_params![i % LETTERS.len()], "unsub_one_param")
+			.await?;
+		let two: Subscription<Notification<IgnoredAny>> = client
+			.subscribe("sub_params_two", rpc_params![0, 1 + i % LETTERS.len()], "unsub_params_two")
+			.await?;
+		subs.push(one);
+		subs.push(two);
+	}
+
+	let mut trackers: Vec<SeqTracker> = subs.iter().map(|_| SeqTracker::default()).collect();
+	let mut notifications =
+		stream::select_all(subs.into_iter().enumerate().map(|(idx, sub)| sub.map(move |n| (idx, n))));
+	let mut report = ClientReport::default();
+	let deadline = sleep_until(deadline);
+	tokio::pin!(deadline);
+
+	loop {
+		tokio::select! {
+			_ = &mut deadline => break,
+			next = notifications.next() => match next {
+				Some((idx, Ok(Notification::Stamped(stamped)))) => {
+					report.received += 1;
+					report.dropped += trackers[idx].observe(stamped.seq);
+					report.latencies_micros.push(unix_micros().saturating_sub(stamped.sent_at_micros));
+				},
+				Some((_, Ok(Notification::Plain(_)))) =>
+					anyhow::bail!("server sent unstamped notifications, start it with --stamp-notifications"),
+				Some((_, Err(_))) => report.errors += 1,
+				None => break,
+			},
+		}
+	}
+
+	Ok(report)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	let cli = BenchCli::parse();
+	let mut config = Config::load(cli.server)?;
+
+	tracing_subscriber::FmtSubscriber::builder()
+		.with_env_filter(tracing_subscriber::EnvFilter::try_new(&config.log_filter)?)
+		.try_init()
+		.expect("setting default subscriber failed");
+
+	let url = match cli.url {
+		Some(url) => url,
+		None => {
+			config.stamp_notifications = true;
+			// Every client opens its own connection, with subscriptions to both methods.
+			config.max_connections = config.max_connections.max(cli.clients as u32);
+			config.max_subscriptions_per_connection =
+				config.max_subscriptions_per_connection.max((cli.subscriptions * 2) as u32);
+			format!("ws://{}", run_server(&config).await?)
+		},
+	};
+
+	let duration = Duration::from_secs(cli.duration_secs);
+	let deadline = Instant::now() + duration;
+	let clients: Vec<_> = (0..cli.clients)
+		.map(|_| tokio::spawn(run_client(url.clone(), cli.subscriptions, deadline)))
+		.collect();
+
+	let mut report = ClientReport::default();
+	let mut failed_clients = 0;
+	for client in clients {
+		match client.await? {
+			Ok(client_report) => report.merge(client_report),
+			Err(e) => {
+				eprintln!("client failed: {:#}", e);
+				failed_clients += 1;
+			},
+		}
+	}
+	report.latencies_micros.sort_unstable();
+
+	let secs = duration.as_secs_f64();
+	let total = report.received + report.dropped;
+	println!("url:            {}", url);
+	println!("clients:        {} ({} failed)", cli.clients, failed_clients);
+	println!("subscriptions:  {} per client", cli.subscriptions * 2);
+	println!("duration:       {:.1} s", secs);
+	println!("received:       {} ({:.1} msg/s)", report.received, report.received as f64 / secs);
+	println!(
+		"dropped:        {} ({:.2}%)",
+		report.dropped,
+		if total == 0 { 0.0 } else { report.dropped as f64 * 100.0 / total as f64 }
+	);
+	println!("errors:         {}", report.errors);
+	for p in [50.0, 90.0, 99.0, 99.9, 100.0] {
+		if let Some(micros) = percentile(&report.latencies_micros, p) {
+			println!("latency p{:<5} {:.3} ms", p, micros as f64 / 1000.0);
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counts_gaps_in_sequence_numbers() {
+		let mut tracker = SeqTracker::default();
+		assert_eq!(tracker.observe(0), 0);
+		assert_eq!(tracker.observe(1), 0);
+		assert_eq!(tracker.observe(5), 3);
+		assert_eq!(tracker.observe(6), 0);
+	}
+
+	#[test]
+	fn first_notifications_missing_count_as_dropped() {
+		let mut tracker = SeqTracker::default();
+		assert_eq!(tracker.observe(2), 2);
+	}
+
+	#[test]
+	fn nearest_rank_percentiles() {
+		let sorted: Vec<u64> = (1..=100).collect();
+		assert_eq!(percentile(&sorted, 50.0), Some(50));
+		assert_eq!(percentile(&sorted, 99.0), Some(99));
+		assert_eq!(percentile(&sorted, 100.0), Some(100));
+		assert_eq!(percentile(&[7], 0.0), Some(7));
+		assert_eq!(percentile(&[], 50.0), None);
+	}
+}
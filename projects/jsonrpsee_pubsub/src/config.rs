@@ -0,0 +1,191 @@
+//! Server configuration loaded from a TOML file and overridden by CLI flags.
+//!
+//! Every field has a default, so the config file only needs to list what differs from it.
+//! Flags given on the command line (or through `RUST_LOG` for the log filter) win over the file.
+
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use clap::Parser;
+use serde::Deserialize;
+
+use crate::{MESSAGE_BUFFER_CAPACITY, TICK_INTERVAL};
+
+/// Settings used to start the pubsub server.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	/// Address the server listens on. Port 0 picks an ephemeral port.
+	pub bind_address: SocketAddr,
+	/// Maximum number of concurrent connections.
+	pub max_connections: u32,
+	/// Number of messages buffered per connection before notifications are dropped.
+	pub message_buffer_capacity: u32,
+	/// Interval between two notifications on every subscription, in milliseconds.
+	pub tick_interval_ms: u64,
+	/// Maximum number of active subscriptions on a single connection.
+	pub max_subscriptions_per_connection: u32,
+	/// `tracing_subscriber::EnvFilter` directives, e.g. `info,jsonrpsee=debug`.
+	pub log_filter: String,
+	/// Wrap notifications with a sequence number and send timestamp, see [`crate::Stamped`].
+	pub stamp_notifications: bool,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			bind_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+			max_connections: 100,
+			message_buffer_capacity: MESSAGE_BUFFER_CAPACITY,
+			tick_interval_ms: TICK_INTERVAL.as_millis() as u64,
+			max_subscriptions_per_connection: 1024,
+			log_filter: String::from("info"),
+			stamp_notifications: false,
+		}
+	}
+}
+
+impl Config {
+	/// Parses a config from the contents of a TOML file.
+	pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+		toml::from_str(contents).context("invalid pubsub config")
+	}
+
+	/// Reads and parses the TOML file at `path`.
+	pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+		let contents =
+			fs::read_to_string(path).with_context(|| format!("reading config file {}", path.display()))?;
+		Self::from_toml(&contents).with_context(|| format!("in config file {}", path.display()))
+	}
+
+	/// Builds the config from the file named by `--config` (if any) and applies the CLI overrides.
+	pub fn load(cli: Cli) -> anyhow::Result<Self> {
+		let mut config = match &cli.config {
+			Some(path) => Self::from_file(path)?,
+			None => Self::default(),
+		};
+		cli.apply(&mut config);
+		config.validate()?;
+		Ok(config)
+	}
+
+	/// Rejects settings the server cannot run with.
+	pub fn validate(&self) -> anyhow::Result<()> {
+		// `tokio::time::interval` panics on a zero period.
+		ensure!(self.tick_interval_ms > 0, "invalid pubsub config: tick_interval_ms must be greater than 0");
+		Ok(())
+	}
+
+	pub fn tick_interval(&self) -> Duration {
+		Duration::from_millis(self.tick_interval_ms)
+	}
+}
+
+/// Command line flags of the `jsonrpsee_pubsub` binary.
+#[derive(Debug, Default, Parser)]
+#[command(about = "JSON-RPC pubsub example server")]
+pub struct Cli {
+	/// Path to a TOML config file.
+	#[arg(long)]
+	pub config: Option<PathBuf>,
+	#[arg(long)]
+	pub bind_address: Option<SocketAddr>,
+	#[arg(long)]
+	pub max_connections: Option<u32>,
+	#[arg(long)]
+	pub message_buffer_capacity: Option<u32>,
+	#[arg(long)]
+	pub tick_interval_ms: Option<u64>,
+	#[arg(long)]
+	pub max_subscriptions_per_connection: Option<u32>,
+	/// Log filter directives. Falls back to `RUST_LOG`, then to the config file.
+	#[arg(long, env = "RUST_LOG")]
+	pub log_filter: Option<String>,
+	/// Wrap notifications with a sequence number and send timestamp, as `pubsub-bench` expects.
+	#[arg(long)]
+	pub stamp_notifications: bool,
+}
+
+impl Cli {
+	fn apply(self, config: &mut Config) {
+		if let Some(bind_address) = self.bind_address {
+			config.bind_address = bind_address;
+		}
+		if let Some(max_connections) = self.max_connections {
+			config.max_connections = max_connections;
+		}
+		if let Some(message_buffer_capacity) = self.message_buffer_capacity {
+			config.message_buffer_capacity = message_buffer_capacity;
+		}
+		if let Some(tick_interval_ms) = self.tick_interval_ms {
+			config.tick_interval_ms = tick_interval_ms;
+		}
+		if let Some(max_subscriptions_per_connection) = self.max_subscriptions_per_connection {
+			config.max_subscriptions_per_connection = max_subscriptions_per_connection;
+		}
+		if let Some(log_filter) = self.log_filter {
+			config.log_filter = log_filter;
+		}
+		if self.stamp_notifications {
+			config.stamp_notifications = true;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_fields_use_defaults() {
+		let config = Config::from_toml("bind_address = \"0.0.0.0:9944\"").unwrap();
+		assert_eq!(config.bind_address, "0.0.0.0:9944".parse().unwrap());
+		assert_eq!(config.message_buffer_capacity, MESSAGE_BUFFER_CAPACITY);
+		assert_eq!(config.tick_interval(), TICK_INTERVAL);
+	}
+
+	#[test]
+	fn example_config_matches_defaults() {
+		let config = Config::from_toml(include_str!("../pubsub.example.toml")).unwrap();
+		assert_eq!(config, Config::default());
+	}
+
+	#[test]
+	fn rejects_unknown_fields() {
+		assert!(Config::from_toml("tick_interval = 5").is_err());
+	}
+
+	#[test]
+	fn cli_flags_override_file() {
+		let mut config = Config::from_toml(
+			"max_connections = 5\ntick_interval_ms = 1000\nlog_filter = \"debug\"",
+		)
+		.unwrap();
+		let cli = Cli::parse_from([
+			"jsonrpsee_pubsub",
+			"--tick-interval-ms",
+			"50",
+			"--log-filter",
+			"trace",
+		]);
+		cli.apply(&mut config);
+
+		assert_eq!(config.max_connections, 5);
+		assert_eq!(config.tick_interval_ms, 50);
+		assert_eq!(config.log_filter, "trace");
+	}
+
+	#[test]
+	fn rejects_zero_tick_interval() {
+		let config = Config::from_toml("tick_interval_ms = 0").unwrap();
+		assert!(config.validate().is_err());
+
+		let cli = Cli::parse_from(["jsonrpsee_pubsub", "--tick-interval-ms", "0"]);
+		let err = Config::load(cli).unwrap_err();
+		assert!(err.to_string().contains("tick_interval_ms"), "{}", err);
+		assert!(Config::load(Cli::default()).is_ok());
+	}
+}
@@ -0,0 +1,191 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Subscription server used by the `jsonrpsee_pubsub` binary.
+//!
+//! The server is split out of `main` so that integration tests can start it on an ephemeral port
+//! and talk to it with a real WebSocket client.
+
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{Stream, StreamExt};
+use jsonrpsee::core::Serialize;
+use jsonrpsee::server::{RpcModule, Server, ServerHandle, SubscriptionMessage, TrySendError};
+use jsonrpsee::PendingSubscriptionSink;
+use tokio::time::interval;
+use tokio_stream::wrappers::IntervalStream;
+
+pub mod config;
+
+pub use config::{Cli, Config};
+
+/// Letters emitted by `sub_one_param` and sliced by `sub_params_two`.
+pub const LETTERS: &str = "abcdefghijklmnopqrstuvxyz";
+
+/// Default number of messages buffered per connection before `try_send` reports the sink as full.
+pub const MESSAGE_BUFFER_CAPACITY: u32 = 10;
+
+/// Default interval between two notifications on every subscription.
+pub const TICK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Notification sent on every tick.
+///
+/// Without `stamp_notifications` only the bare item is sent, so existing clients keep receiving
+/// e.g. `"cde"`. With it, the item is wrapped together with a per-subscription sequence number and
+/// the server-side send time, which lets clients measure latency and count dropped messages.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Notification<T> {
+	Stamped(Stamped<T>),
+	Plain(T),
+}
+
+/// A notification item together with its position in the subscription and its send time, sent
+/// as `{ "seq": 7, "sent_at_micros": 1678017600250000, "item": "cde" }`.
+///
+/// Every subscription counts its own ticks, and the server drops notifications instead of
+/// waiting when a connection's message buffer is full. A client that last saw `seq` n and then
+/// receives m has therefore missed the `m - n - 1` notifications in between.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Stamped<T> {
+	/// Index of the tick that produced this notification, starting at 0 and increasing by one per
+	/// tick whether or not the notification could be sent.
+	pub seq: u64,
+	/// Server wall-clock time when the notification was produced, in microseconds since the Unix
+	/// epoch. Subtracted from the receive time it gives the latency, if both clocks agree.
+	pub sent_at_micros: u64,
+	/// The item an unstamped notification consists of.
+	pub item: T,
+}
+
+/// Current wall-clock time in microseconds since the Unix epoch.
+pub fn unix_micros() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_micros() as u64).unwrap_or_default()
+}
+
+/// Emits `item` once every `tick_interval`, stamped if requested.
+fn notifications<T: Clone>(
+	tick_interval: Duration,
+	stamp: bool,
+	item: T,
+) -> impl Stream<Item = Notification<T>> + Unpin {
+	IntervalStream::new(interval(tick_interval)).enumerate().map(move |(seq, _)| {
+		let item = item.clone();
+		if stamp {
+			Notification::Stamped(Stamped { seq: seq as u64, sent_at_micros: unix_micros(), item })
+		} else {
+			Notification::Plain(item)
+		}
+	})
+}
+
+/// Builds the module with the `sub_one_param` and `sub_params_two` subscriptions, which notify
+/// once every `config.tick_interval_ms`.
+pub fn rpc_module(config: &Config) -> RpcModule<()> {
+	let tick_interval = config.tick_interval();
+	let stamp = config.stamp_notifications;
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("sub_one_param", "sub_one_param", "unsub_one_param", move |params, pending, _| async move {
+			// we are doing this verbose way to get a customized reject error on the subscription.
+			let idx = match params.one::<usize>() {
+				Ok(p) => p,
+				Err(e) => {
+					let _ = pending.reject(e).await;
+					return Ok(());
+				}
+			};
+
+			let item = LETTERS.chars().nth(idx);
+			let stream = notifications(tick_interval, stamp, item);
+
+			pipe_from_stream_and_drop(pending, stream).await.map_err(Into::into)
+		})
+		.unwrap();
+	module
+		.register_subscription("sub_params_two", "params_two", "unsub_params_two", move |params, pending, _| async move {
+			let (one, two) = params.parse::<(usize, usize)>()?;
+
+			let item = &LETTERS[one..two];
+			let stream = notifications(tick_interval, stamp, item);
+			pipe_from_stream_and_drop(pending, stream).await.map_err(Into::into)
+		})
+		.unwrap();
+	module
+}
+
+/// Starts a server with the given module on `config.bind_address`.
+///
+/// Returns the bound address together with the handle that can be used to stop the server.
+pub async fn start_server(module: RpcModule<()>, config: &Config) -> anyhow::Result<(SocketAddr, ServerHandle)> {
+	let server = Server::builder()
+		.max_connections(config.max_connections)
+		.max_subscriptions_per_connection(config.max_subscriptions_per_connection)
+		.set_message_buffer_capacity(config.message_buffer_capacity)
+		.build(config.bind_address)
+		.await?;
+	let addr = server.local_addr()?;
+	let handle = server.start(module);
+
+	Ok((addr, handle))
+}
+
+pub async fn run_server(config: &Config) -> anyhow::Result<SocketAddr> {
+	let (addr, handle) = start_server(rpc_module(config), config).await?;
+
+	// In this example we don't care about doing shutdown so let's it run forever.
+	// You may use the `ServerHandle` to shut it down or manage it yourself.
+	tokio::spawn(handle.stopped());
+
+	Ok(addr)
+}
+
+pub async fn pipe_from_stream_and_drop<T: Serialize>(
+	pending: PendingSubscriptionSink,
+	mut stream: impl Stream<Item = T> + Unpin,
+) -> Result<(), anyhow::Error> {
+	let mut sink = pending.accept().await?;
+
+	loop {
+		tokio::select! {
+			_ = sink.closed() => break Err(anyhow::anyhow!("Subscription was closed")),
+			maybe_item = stream.next() => {
+				let item = match maybe_item {
+					Some(item) => item,
+					None => break Err(anyhow::anyhow!("Subscription was closed")),
+				};
+				let msg = SubscriptionMessage::from_json(&item)?;
+				match sink.try_send(msg) {
+					Ok(_) => (),
+					Err(TrySendError::Closed(_)) => break Err(anyhow::anyhow!("Subscription was closed")),
+					// channel is full, let's be naive an just drop the message.
+					Err(TrySendError::Full(_)) => (),
+				}
+			}
+		}
+	}
+}
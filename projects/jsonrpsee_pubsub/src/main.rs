@@ -24,109 +24,31 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use std::net::SocketAddr;
-use std::time::Duration;
-
-use futures::{Stream, StreamExt};
-use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
-use jsonrpsee::core::Serialize;
-use jsonrpsee::server::{RpcModule, Server, SubscriptionMessage, TrySendError};
-use jsonrpsee::ws_client::WsClientBuilder;
-use jsonrpsee::{rpc_params, PendingSubscriptionSink};
-use tokio::time::interval;
-use tokio_stream::wrappers::IntervalStream;
+use clap::Parser;
+use jsonrpsee_pubsub::{rpc_module, start_server, Cli, Config};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+	let config = Config::load(Cli::parse())?;
+
 	tracing_subscriber::FmtSubscriber::builder()
-		.with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+		.with_env_filter(tracing_subscriber::EnvFilter::try_new(&config.log_filter)?)
 		.try_init()
 		.expect("setting default subscriber failed");
 
-	let addr = run_server().await?;
-	let url = format!("ws://{}", addr);
-
-	let client = WsClientBuilder::default().build(&url).await?;
-
-	// Subscription with a single parameter
-	let mut sub_params_one: Subscription<Option<char>> =
-		client.subscribe("sub_one_param", rpc_params![3], "unsub_one_param").await?;
-	tracing::info!("subscription with one param: {:?}", sub_params_one.next().await);
-
-	// Subscription with multiple parameters
-	let mut sub_params_two: Subscription<String> =
-		client.subscribe("sub_params_two", rpc_params![2, 5], "unsub_params_two").await?;
-	tracing::info!("subscription with two params: {:?}", sub_params_two.next().await);
+	let (addr, handle) = start_server(rpc_module(&config), &config).await?;
+	tracing::info!("listening on ws://{}", addr);
+
+	// Serve until the server stops on its own or is interrupted.
+	tokio::select! {
+		_ = handle.clone().stopped() => (),
+		interrupted = tokio::signal::ctrl_c() => {
+			interrupted?;
+			tracing::info!("shutting down");
+			handle.stop()?;
+			handle.stopped().await;
+		},
+	}
 
 	Ok(())
 }
-
-async fn run_server() -> anyhow::Result<SocketAddr> {
-	const LETTERS: &str = "abcdefghijklmnopqrstuvxyz";
-	let server = Server::builder().set_message_buffer_capacity(10).build("127.0.0.1:0").await?;
-	let mut module = RpcModule::new(());
-	module
-		.register_subscription("sub_one_param", "sub_one_param", "unsub_one_param", |params, pending, _| async move {
-			// we are doing this verbose way to get a customized reject error on the subscription.
-			let idx = match params.one::<usize>() {
-				Ok(p) => p,
-				Err(e) => {
-					let _ = pending.reject(e).await;
-					return Ok(());
-				}
-			};
-
-			let item = LETTERS.chars().nth(idx);
-
-			let interval = interval(Duration::from_millis(200));
-			let stream = IntervalStream::new(interval).map(move |_| item);
-
-			pipe_from_stream_and_drop(pending, stream).await.map_err(Into::into)
-		})
-		.unwrap();
-	module
-		.register_subscription("sub_params_two", "params_two", "unsub_params_two", |params, pending, _| async move {
-			let (one, two) = params.parse::<(usize, usize)>()?;
-
-			let item = &LETTERS[one..two];
-			let interval = interval(Duration::from_millis(200));
-			let stream = IntervalStream::new(interval).map(move |_| item);
-			pipe_from_stream_and_drop(pending, stream).await.map_err(Into::into)
-		})
-		.unwrap();
-
-	let addr = server.local_addr()?;
-	let handle = server.start(module);
-
-	// In this example we don't care about doing shutdown so let's it run forever.
-	// You may use the `ServerHandle` to shut it down or manage it yourself.
-	tokio::spawn(handle.stopped());
-
-	Ok(addr)
-}
-
-pub async fn pipe_from_stream_and_drop<T: Serialize>(
-	pending: PendingSubscriptionSink,
-	mut stream: impl Stream<Item = T> + Unpin,
-) -> Result<(), anyhow::Error> {
-	let mut sink = pending.accept().await?;
-
-	loop {
-		tokio::select! {
-			_ = sink.closed() => break Err(anyhow::anyhow!("Subscription was closed")),
-			maybe_item = stream.next() => {
-				let item = match maybe_item {
-					Some(item) => item,
-					None => break Err(anyhow::anyhow!("Subscription was closed")),
-				};
-				let msg = SubscriptionMessage::from_json(&item)?;
-				match sink.try_send(msg) {
-					Ok(_) => (),
-					Err(TrySendError::Closed(_)) => break Err(anyhow::anyhow!("Subscription was closed")),
-					// channel is full, let's be naive an just drop the message.
-					Err(TrySendError::Full(_)) => (),
-				}
-			}
-		}
-	}
-}
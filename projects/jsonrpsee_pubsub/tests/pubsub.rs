@@ -0,0 +1,198 @@
+//! Integration tests for the pubsub server.
+//!
+//! Every test runs on tokio's paused clock, so the 200 ms subscription ticks are auto-advanced
+//! instead of being waited for on the wall clock.
+
+use futures::StreamExt;
+use jsonrpsee::client_transport::ws::{Receiver, Url, WsTransportClientBuilder};
+use jsonrpsee::core::client::{
+	ReceivedMessage, Subscription, SubscriptionClientT, TransportReceiverT, TransportSenderT,
+};
+use jsonrpsee::rpc_params;
+use jsonrpsee::server::{RpcModule, ServerHandle};
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use jsonrpsee_pubsub::{
+	pipe_from_stream_and_drop, rpc_module, start_server, Config, Notification, Stamped, TICK_INTERVAL,
+};
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep};
+use tokio_stream::wrappers::IntervalStream;
+
+/// Module with a `sub_counter` subscription that emits 0, 1, 2, ... on every tick and reports
+/// the result of `pipe_from_stream_and_drop` through `done` once the subscription ends.
+fn counter_module(done: mpsc::UnboundedSender<anyhow::Result<()>>) -> RpcModule<()> {
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("sub_counter", "counter", "unsub_counter", move |_, pending, _| {
+			let done = done.clone();
+			async move {
+				let stream = IntervalStream::new(interval(TICK_INTERVAL)).enumerate().map(|(i, _)| i);
+				let _ = done.send(pipe_from_stream_and_drop(pending, stream).await);
+				Ok(())
+			}
+		})
+		.unwrap();
+	module
+}
+
+/// Ticks a client of `sub_padded_counter` leaves unread in `drops_messages_when_buffer_is_full`.
+const UNREAD_TICKS: u32 = 500;
+
+/// Module with a `sub_padded_counter` subscription that emits `[i, padding]` on every tick, with
+/// 64 KiB of padding so that a few hundred unread notifications overflow the socket buffers.
+fn padded_counter_module() -> RpcModule<()> {
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("sub_padded_counter", "padded_counter", "unsub_padded_counter", |_, pending, _| async move {
+			let padding = "x".repeat(64 * 1024);
+			let stream =
+				IntervalStream::new(interval(TICK_INTERVAL)).enumerate().map(move |(i, _)| (i, padding.clone()));
+			pipe_from_stream_and_drop(pending, stream).await.map_err(Into::into)
+		})
+		.unwrap();
+	module
+}
+
+/// Reads the next text message of a bare transport as JSON.
+async fn receive_json(receiver: &mut Receiver) -> serde_json::Value {
+	match receiver.receive().await.unwrap() {
+		ReceivedMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+		other => panic!("expected a text message, got {:?}", other),
+	}
+}
+
+/// Starts the server with the production subscriptions plus `sub_counter` and connects a client.
+async fn start(done: mpsc::UnboundedSender<anyhow::Result<()>>) -> (WsClient, ServerHandle) {
+	start_with(&Config::default(), done).await
+}
+
+async fn start_with(
+	config: &Config,
+	done: mpsc::UnboundedSender<anyhow::Result<()>>,
+) -> (WsClient, ServerHandle) {
+	let mut module = rpc_module(config);
+	module.merge(counter_module(done)).unwrap();
+	let (addr, handle) = start_server(module, config).await.unwrap();
+	let client = WsClientBuilder::default().build(format!("ws://{}", addr)).await.unwrap();
+	(client, handle)
+}
+
+#[tokio::test(start_paused = true)]
+async fn subscribes_and_unsubscribes() {
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let (client, handle) = start(tx).await;
+
+	let mut one: Subscription<Option<char>> =
+		client.subscribe("sub_one_param", rpc_params![3], "unsub_one_param").await.unwrap();
+	assert_eq!(one.next().await.unwrap().unwrap(), Some('d'));
+	one.unsubscribe().await.unwrap();
+
+	let mut two: Subscription<String> =
+		client.subscribe("sub_params_two", rpc_params![2, 5], "unsub_params_two").await.unwrap();
+	assert_eq!(two.next().await.unwrap().unwrap(), "cde");
+	two.unsubscribe().await.unwrap();
+
+	handle.stop().unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn stamps_notifications_when_enabled() {
+	let config = Config { stamp_notifications: true, ..Config::default() };
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let (client, handle) = start_with(&config, tx).await;
+
+	let sub: Subscription<Notification<String>> =
+		client.subscribe("sub_params_two", rpc_params![2, 5], "unsub_params_two").await.unwrap();
+	let received: Vec<_> = sub.take(3).map(Result::unwrap).collect().await;
+	for (seq, notification) in received.into_iter().enumerate() {
+		match notification {
+			Notification::Stamped(Stamped { seq: got, sent_at_micros, item }) => {
+				assert_eq!(got, seq as u64);
+				assert!(sent_at_micros > 0);
+				assert_eq!(item, "cde");
+			},
+			Notification::Plain(item) => panic!("expected a stamped notification, got {:?}", item),
+		}
+	}
+
+	handle.stop().unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn rejects_invalid_params() {
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let (client, handle) = start(tx).await;
+
+	let not_a_number: Result<Subscription<Option<char>>, _> =
+		client.subscribe("sub_one_param", rpc_params!["three"], "unsub_one_param").await;
+	assert!(not_a_number.is_err());
+
+	let missing_param: Result<Subscription<String>, _> =
+		client.subscribe("sub_params_two", rpc_params![2], "unsub_params_two").await;
+	assert!(missing_param.is_err());
+
+	handle.stop().unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn delivers_messages_in_order() {
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let (client, handle) = start(tx).await;
+
+	let sub: Subscription<usize> =
+		client.subscribe("sub_counter", rpc_params![], "unsub_counter").await.unwrap();
+	let received: Vec<usize> = sub.take(25).map(Result::unwrap).collect().await;
+	assert_eq!(received, (0..25).collect::<Vec<_>>());
+
+	handle.stop().unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn detects_closed_sink() {
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let (client, handle) = start(tx).await;
+
+	let mut sub: Subscription<usize> =
+		client.subscribe("sub_counter", rpc_params![], "unsub_counter").await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), 0);
+	sub.unsubscribe().await.unwrap();
+
+	let err = rx.recv().await.unwrap().unwrap_err();
+	assert_eq!(err.to_string(), "Subscription was closed");
+
+	handle.stop().unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn drops_messages_when_buffer_is_full() {
+	let config = Config::default();
+	let mut module = rpc_module(&config);
+	module.merge(padded_counter_module()).unwrap();
+	let (addr, handle) = start_server(module, &config).await.unwrap();
+
+	// A bare transport only reads the socket when asked to, unlike `WsClient` which drains it in
+	// the background.
+	let url = Url::parse(&format!("ws://{}", addr)).unwrap();
+	let (mut sender, mut receiver) = WsTransportClientBuilder::default().build(url).await.unwrap();
+	let subscribe = r#"{"jsonrpc":"2.0","id":0,"method":"sub_padded_counter","params":[]}"#;
+	sender.send(subscribe.to_string()).await.unwrap();
+	assert!(receive_json(&mut receiver).await["result"].is_number());
+
+	// Far more ticks than the socket buffers and `MESSAGE_BUFFER_CAPACITY` messages hold, but fewer
+	// than jsonrpsee's default buffer of 1024 messages would, so the gap comes from the setting.
+	sleep(TICK_INTERVAL * UNREAD_TICKS).await;
+
+	let mut seqs = vec![];
+	loop {
+		let seq = receive_json(&mut receiver).await["params"]["result"][0].as_u64().unwrap();
+		seqs.push(seq);
+		if seq >= u64::from(UNREAD_TICKS) {
+			break;
+		}
+	}
+	assert_eq!(seqs[0], 0);
+	assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]), "out of order: {:?}", seqs);
+	assert!(seqs.windows(2).any(|pair| pair[1] > pair[0] + 1), "expected a gap, got {:?}", seqs);
+
+	handle.stop().unwrap();
+}